@@ -8,23 +8,31 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
 
-use crate::ingest::HttpIngestor;
-use crate::models::{Log, Priority};
-use crate::worker::Signal;
+use crate::diagnostics::Reporter;
+use crate::models::Log;
+use crate::sinks::Sink;
+use crate::stats::Counters;
+use crate::throttle::Throttle;
 
 pub enum BacklogSignal {
     Entries(Vec<Log>),
-    Flush,
+    Flush(flume::Sender<(usize, usize)>),
+    Persist(Vec<Log>, flume::Sender<bool>),
     Exit,
 }
 
 pub struct Backlog {
     signal_receiver: flume::Receiver<BacklogSignal>,
-    log_sender: flume::Sender<Signal>,
-    ingest: Arc<HttpIngestor>,
-    dirs: ProjectDirs,
+    reporter: Arc<Reporter>,
+    sink: Arc<dyn Sink>,
+    throttle: Option<Arc<Throttle>>,
+    counters: Arc<Counters>,
+    path: Option<PathBuf>,
     exit: bool,
     queue: Vec<Log>,
+    disk_entries: usize,
+    disk_bytes: usize,
+    reported: (i64, i64),
     backoff_multiplier: u32,
     pub is_empty: Arc<AtomicBool>,
     pub signal_sender: flume::Sender<BacklogSignal>,
@@ -38,19 +46,31 @@ const BACKLOG_MAX_CHECK_INTERVAL: Duration = Duration::from_secs(120);
 const BACKLOG_MIN_LOOP_INTERVAL: Duration = Duration::from_millis(100);
 
 impl Backlog {
-    pub fn new(ingest: Arc<HttpIngestor>, log_sender: flume::Sender<Signal>) -> Self {
-        let dirs = ProjectDirs::from("cloud.dlog", "", "dlog").unwrap();
+    pub fn new(
+        sink: Arc<dyn Sink>,
+        file_name: String,
+        throttle: Option<Arc<Throttle>>,
+        counters: Arc<Counters>,
+        reporter: Arc<Reporter>,
+    ) -> Self {
+        // The path is resolved once, so an unwritable directory is only reported once
+        let path = get_path(&file_name, &reporter);
         let (signal_sender, signal_receiver) = flume::unbounded();
         let (flush_sender, flush_receiver) = flume::unbounded();
 
         Self {
-            dirs,
-            ingest,
-            log_sender,
+            path,
+            sink,
+            throttle,
+            counters,
+            reporter,
             signal_receiver,
             exit: false,
             backoff_multiplier: 1,
             queue: Vec::with_capacity(BACKLOG_CHUNK_SIZE),
+            disk_entries: 0,
+            disk_bytes: 0,
+            reported: (0, 0),
             is_empty: Arc::new(AtomicBool::new(true)),
             signal_sender,
             flush_sender,
@@ -63,7 +83,8 @@ impl Backlog {
         self.load_from_disk().await;
 
         if let Err(err) = self.flush_sender.send_async(()).await {
-            eprintln!("[dlog::worker] Failed to receive ready signal: {}", err);
+            self.reporter
+                .report(format!("[dlog::worker] Failed to receive ready signal: {}", err));
         }
 
         while !self.exit {
@@ -75,56 +96,91 @@ impl Backlog {
                 last_check = Instant::now();
                 self.retry().await;
             }
+            self.report();
         }
 
         self.flush_to_disk().await;
+        self.report();
+        if let Err(err) = self.flush_sender.send_async(()).await {
+            self.reporter
+                .report(format!("[dlog::backlog] Cannot send exit signal: {}", err));
+        }
     }
 
     async fn receive(&mut self, signal: Result<BacklogSignal, flume::RecvError>) {
-        self.is_empty.store(false, Ordering::Relaxed);
         match signal {
-            Ok(BacklogSignal::Entries(mut logs)) => self.queue.append(&mut logs),
-            Ok(BacklogSignal::Flush) => {
+            Ok(BacklogSignal::Entries(mut logs)) => {
+                self.is_empty.store(false, Ordering::Relaxed);
+                self.queue.append(&mut logs);
+            }
+            Ok(BacklogSignal::Persist(mut logs, reply)) => {
+                self.is_empty.store(false, Ordering::Relaxed);
+                self.queue.append(&mut logs);
+                let persisted = self.flush_to_disk().await;
+                if let Err(err) = reply.send_async(persisted).await {
+                    self.reporter
+                        .report(format!("[dlog::backlog] Cannot send persist signal: {}", err));
+                }
+            }
+            Ok(BacklogSignal::Flush(reply)) => {
                 while let Ok(signal) = self.signal_receiver.try_recv() {
                     if let BacklogSignal::Entries(mut logs) = signal {
+                        self.is_empty.store(false, Ordering::Relaxed);
                         self.queue.append(&mut logs);
                     }
                 }
 
-                self.retry().await;
-                if let Err(err) = self.flush_sender.send_async(()).await {
-                    eprintln!("[dlog::backlog] Cannot send flush signal: {}", err);
+                let delivered = self.retry().await;
+                if let Err(err) = reply.send_async((delivered, self.queue.len() + self.disk_entries)).await {
+                    self.reporter
+                        .report(format!("[dlog::backlog] Cannot send flush signal: {}", err));
                 }
             }
             _ => self.exit = true,
         }
     }
 
-    async fn retry(&mut self) {
-        if !self.queue.is_empty() && self.ingest.check().await {
+    async fn retry(&mut self) -> usize {
+        let mut delivered = 0;
+        if !self.queue.is_empty() && self.sink.check().await {
             self.load_from_disk().await;
-            self.is_empty.store(true, Ordering::Relaxed);
-            self.send_log(format!("[dlog] Retrying ingest for {} logs", self.queue.len()))
-                .await;
+            self.reporter
+                .report(format!("[dlog] Retrying ingest for {} logs", self.queue.len()));
             while !self.queue.is_empty() {
-                let mut logs = self
+                let logs = self
                     .queue
                     .drain(..min(self.queue.len(), BACKLOG_CHUNK_SIZE))
                     .collect::<Vec<Log>>();
 
-                if let Err(err) = self.ingest.log_async(&logs).await {
+                if let Some(throttle) = &self.throttle {
+                    throttle.acquire(&logs).await;
+                }
+
+                let count = logs.len();
+                self.counters.retried(count);
+                if let Err(err) = self.sink.log_async(&logs).await {
+                    self.counters.error(&err.message.text);
+                    let (mut logs, rejected, log) = err.split(logs);
+                    self.counters.sent(count - logs.len() - rejected);
+                    self.counters.dropped(rejected);
+                    delivered += count - logs.len() - rejected;
                     self.queue.append(&mut logs);
-                    self.queue.push(err);
+                    self.reporter.report(log.text);
                     self.backoff_multiplier += 1;
-                    self.send_log(format!("[dlog] Will retry in {} seconds", self.backoff().as_secs()))
-                        .await;
-                    return;
+                    self.reporter
+                        .report(format!("[dlog] Will retry in {} seconds", self.backoff().as_secs()));
+                    return delivered;
                 }
+                self.counters.sent(count);
+                delivered += count;
             }
+            // Only now the backlog is drained, a failed send above keeps the status at backlogging
+            self.is_empty.store(true, Ordering::Relaxed);
             self.backoff_multiplier = 1;
         } else if !self.queue.is_empty() {
             self.flush_to_disk().await;
         }
+        delivered
     }
 
     fn backoff(&self) -> Duration {
@@ -134,44 +190,76 @@ impl Backlog {
         )
     }
 
-    async fn flush_to_disk(&mut self) {
-        if !self.queue.is_empty() {
-            if let Some(mut file) = Self::get_file(&self.dirs, true) {
-                for log in self.queue.drain(..self.queue.len()) {
-                    if let Ok(ctn) = serde_json::to_string(&log) {
-                        if let Err(err) = writeln!(file, "{}", ctn) {
-                            eprintln!("[dlog::backlog] Cannot write log to cache: {}", err);
-                        }
+    /// Returns whether all logs were written to disk
+    async fn flush_to_disk(&mut self) -> bool {
+        if self.queue.is_empty() {
+            return true;
+        }
+
+        let mut file = match self.get_file(true) {
+            Some(file) => file,
+            None => return false,
+        };
+
+        let mut persisted = true;
+        for log in self.queue.drain(..self.queue.len()) {
+            match serde_json::to_string(&log) {
+                Ok(ctn) => match writeln!(file, "{}", ctn) {
+                    Ok(_) => {
+                        self.disk_entries += 1;
+                        self.disk_bytes += ctn.len() + 1;
                     }
+                    Err(err) => {
+                        self.reporter
+                            .report(format!("[dlog::backlog] Cannot write log to cache: {}", err));
+                        self.counters.dropped(1);
+                        persisted = false;
+                    }
+                },
+                Err(_) => {
+                    self.counters.dropped(1);
+                    persisted = false;
                 }
             }
         }
+
+        if let Err(err) = file.sync_data() {
+            self.reporter
+                .report(format!("[dlog::backlog] Cannot sync cache to disk: {}", err));
+            persisted = false;
+        }
+        persisted
     }
 
     async fn load_from_disk(&mut self) {
-        if let Some(file) = Self::get_file(&self.dirs, false) {
+        if let Some(file) = self.get_file(false) {
             let reader = BufReader::new(file);
             let mut logs = Vec::new();
             for line in reader.lines().flatten() {
-                if let Ok(log) = serde_json::from_str::<Log>(&line) {
-                    logs.push(log);
+                match serde_json::from_str::<Log>(&line) {
+                    Ok(log) => logs.push(log),
+                    Err(_) => self.counters.dropped(1),
                 }
             }
             logs.append(&mut self.queue);
             self.queue = logs;
-            Self::remove_file(&self.dirs);
+            self.disk_entries = 0;
+            self.disk_bytes = 0;
+            self.remove_file();
         }
     }
 
-    async fn send_log(&self, message: impl Into<String>) {
-        let _ = self
-            .log_sender
-            .send_async(Signal::Log(Log::new(Priority::Trace, message)))
-            .await;
+    fn report(&mut self) {
+        let current = ((self.queue.len() + self.disk_entries) as i64, self.disk_bytes as i64);
+        if current != self.reported {
+            self.counters
+                .backlog(current.0 - self.reported.0, current.1 - self.reported.1);
+            self.reported = current;
+        }
     }
 
-    fn get_file(dirs: &ProjectDirs, create: bool) -> Option<std::fs::File> {
-        match Self::get_path(dirs) {
+    fn get_file(&self, create: bool) -> Option<std::fs::File> {
+        match &self.path {
             None => None,
             Some(path) => OpenOptions::new()
                 .read(true)
@@ -182,18 +270,19 @@ impl Backlog {
         }
     }
 
-    fn remove_file(dirs: &ProjectDirs) {
-        if let Some(path) = Self::get_path(dirs) {
+    fn remove_file(&self) {
+        if let Some(path) = &self.path {
             let _ = std::fs::remove_file(path);
         }
     }
+}
 
-    fn get_path(dirs: &ProjectDirs) -> Option<PathBuf> {
-        let path = dirs.config_dir();
-        if !path.exists() && std::fs::create_dir_all(&path).is_err() {
-            eprintln!("[dlog::backlog] Cannot cache logs to {:?}", path);
-            return None;
-        }
-        Some(path.join("backlog.dat"))
+fn get_path(file_name: &str, reporter: &Reporter) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("cloud.dlog", "", "dlog").unwrap();
+    let path = dirs.config_dir();
+    if !path.exists() && std::fs::create_dir_all(path).is_err() {
+        reporter.report(format!("[dlog::backlog] Cannot cache logs to {:?}", path));
+        return None;
     }
+    Some(path.join(file_name))
 }
@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::diagnostics::Diagnostics;
+use crate::models::{Encoding, FlushPolicy, Priority};
+use crate::sinks::{
+    ElasticSink, ElasticSinkOptions, FileSink, FileSinkOptions, FluentSink, FluentSinkOptions, GelfSink, GelfSinkOptions,
+    LokiSink, LokiSinkOptions, Sink, SplunkSink, SplunkSinkOptions,
+};
+
+const DEFAULT_ENDPOINT: &str = "https://log.dlog.sh";
+const DEFAULT_DRAIN_DEADLINE: Duration = Duration::from_secs(3);
+
+pub struct Config {
+    pub(crate) cloud: bool,
+    pub(crate) api_key_file: Option<PathBuf>,
+    pub(crate) endpoints: Vec<String>,
+    pub(crate) encoding: Encoding,
+    pub(crate) streaming: bool,
+    pub(crate) clock_skew_threshold: Option<Duration>,
+    pub(crate) bandwidth_limit: Option<u64>,
+    pub(crate) backlog_bandwidth_limit: Option<u64>,
+    pub(crate) proxy: Option<String>,
+    pub(crate) env_proxy: bool,
+    pub(crate) root_certificates: Vec<PathBuf>,
+    pub(crate) client_certificate: Option<(PathBuf, PathBuf)>,
+    pub(crate) sinks: Vec<Arc<dyn Sink>>,
+    pub(crate) diagnostics: Diagnostics,
+    pub(crate) flush_policies: Vec<(Priority, FlushPolicy)>,
+    pub(crate) drain_deadline: Duration,
+    pub(crate) signal_handler: bool,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self {
+            cloud: true,
+            api_key_file: None,
+            endpoints: vec![String::from(DEFAULT_ENDPOINT)],
+            encoding: Encoding::Json,
+            streaming: false,
+            clock_skew_threshold: None,
+            bandwidth_limit: None,
+            backlog_bandwidth_limit: None,
+            proxy: None,
+            env_proxy: true,
+            root_certificates: Vec::new(),
+            client_certificate: None,
+            sinks: Vec::new(),
+            diagnostics: Diagnostics::Stderr,
+            flush_policies: vec![(Priority::Critical, FlushPolicy::Durable)],
+            drain_deadline: DEFAULT_DRAIN_DEADLINE,
+            signal_handler: false,
+        }
+    }
+
+    pub fn set_cloud(&mut self, enabled: bool) {
+        self.cloud = enabled;
+    }
+
+    /// Reads the API_KEY from the file at `path`, e.g. a mounted secret, instead of using the given
+    /// API_KEY. The file is re-read periodically and the API_KEY is rotated when it changes.
+    pub fn set_api_key_file(&mut self, path: impl Into<PathBuf>) {
+        self.api_key_file = Some(path.into());
+    }
+
+    /// Sets the ingest endpoints in order of preference. If an endpoint fails, delivery continues
+    /// against the next one before logs are written to the backlog.
+    pub fn set_endpoints(&mut self, urls: impl IntoIterator<Item = impl Into<String>>) {
+        self.endpoints = urls.into_iter().map(Into::into).collect();
+    }
+
+    /// Sets the preferred wire format for the dlog cloud. If the server does not support it, dlog
+    /// falls back to JSON.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Streams logs to the dlog cloud over a persistent connection as they arrive instead of
+    /// sending them in batches. Falls back to batches while the connection is down. Cannot be
+    /// combined with a proxy.
+    pub fn set_streaming(&mut self, enabled: bool) {
+        self.streaming = enabled;
+    }
+
+    /// Corrects the timestamps of all logs by the clock skew measured against the dlog cloud
+    /// once it exceeds `threshold`. As the server time has a resolution of one second, the
+    /// threshold should be a few seconds at least.
+    pub fn set_clock_skew_correction(&mut self, threshold: Duration) {
+        self.clock_skew_threshold = Some(threshold);
+    }
+
+    /// Limits the bytes per second uploaded for fresh logs to each remote sink. Local sinks like
+    /// files are not limited. The size of a batch is estimated from the log messages, so the actual
+    /// traffic may differ slightly. `None` or `0` removes the limit.
+    pub fn set_bandwidth_limit(&mut self, bytes_per_second: Option<u64>) {
+        self.bandwidth_limit = bytes_per_second.filter(|val| *val > 0);
+    }
+
+    /// Limits the bytes per second uploaded while replaying the backlog. This budget is separate
+    /// from the one for fresh logs, so a large backlog does not delay them. `None` or `0` removes the
+    /// limit.
+    pub fn set_backlog_bandwidth_limit(&mut self, bytes_per_second: Option<u64>) {
+        self.backlog_bandwidth_limit = bytes_per_second.filter(|val| *val > 0);
+    }
+
+    /// Routes all requests to the dlog cloud through the given proxy. Hosts listed in `NO_PROXY`
+    /// are still contacted directly.
+    pub fn set_proxy(&mut self, url: impl Into<String>) {
+        self.proxy = Some(url.into());
+    }
+
+    /// Controls whether the `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment variables are
+    /// respected. Enabled by default.
+    pub fn set_env_proxy(&mut self, enabled: bool) {
+        self.env_proxy = enabled;
+    }
+
+    /// Trusts the PEM encoded CA certificate at `path` in addition to the built-in root certificates.
+    pub fn add_root_certificate(&mut self, path: impl Into<PathBuf>) {
+        self.root_certificates.push(path.into());
+    }
+
+    /// Presents the PEM encoded client certificate and private key for mutual TLS.
+    pub fn set_client_certificate(&mut self, certificate: impl Into<PathBuf>, key: impl Into<PathBuf>) {
+        self.client_certificate = Some((certificate.into(), key.into()));
+    }
+
+    /// Sets where dlog reports its own problems. By default they are written to stderr and never
+    /// end up in the log stream.
+    pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    /// Sets when logs of the given priority are sent. By default `Critical` logs are sent durably
+    /// and all other logs are sent in batches.
+    pub fn set_flush_policy(&mut self, priority: Priority, policy: FlushPolicy) {
+        self.flush_policies.retain(|(val, _)| *val != priority);
+        self.flush_policies.push((priority, policy));
+    }
+
+    /// Sets how long the logger waits for the remaining logs to be sent when it is cleaned up or
+    /// dropped. Logs which were not sent by then are written to the backlog. Defaults to 3 seconds.
+    pub fn set_drain_deadline(&mut self, deadline: Duration) {
+        self.drain_deadline = deadline;
+    }
+
+    /// Installs a handler for SIGTERM and SIGINT (Ctrl+C on Windows) which drains the logger and
+    /// then exits the process. Disabled by default.
+    pub fn set_signal_handler(&mut self, enabled: bool) {
+        self.signal_handler = enabled;
+    }
+
+    pub fn add_sink(&mut self, sink: impl Sink + 'static) {
+        self.sinks.push(Arc::new(sink));
+    }
+
+    pub fn add_file_sink(&mut self, options: FileSinkOptions) -> Result<(), String> {
+        self.add_sink(FileSink::new(options)?);
+        Ok(())
+    }
+
+    pub fn add_elastic_sink(&mut self, options: ElasticSinkOptions) -> Result<(), String> {
+        self.add_sink(ElasticSink::new(options)?);
+        Ok(())
+    }
+
+    pub fn add_fluent_sink(&mut self, options: FluentSinkOptions) -> Result<(), String> {
+        self.add_sink(FluentSink::new(options)?);
+        Ok(())
+    }
+
+    pub fn add_gelf_sink(&mut self, options: GelfSinkOptions) -> Result<(), String> {
+        self.add_sink(GelfSink::new(options)?);
+        Ok(())
+    }
+
+    pub fn add_loki_sink(&mut self, options: LokiSinkOptions) -> Result<(), String> {
+        self.add_sink(LokiSink::new(options)?);
+        Ok(())
+    }
+
+    pub fn add_splunk_sink(&mut self, options: SplunkSinkOptions) -> Result<(), String> {
+        self.add_sink(SplunkSink::new(options)?);
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::models::{Log, Priority};
+use crate::worker::Signal;
+
+const STREAM_WINDOW: Duration = Duration::from_secs(60);
+
+/// Controls where dlog reports its own problems, e.g. connection errors or backlog retries
+#[derive(Clone, Default)]
+pub enum Diagnostics {
+    /// Writes all messages to stderr
+    #[default]
+    Stderr,
+    /// Discards all messages
+    Silent,
+    /// Passes all messages to the given function
+    Callback(Arc<dyn Fn(&str) + Send + Sync>),
+    /// Adds the messages as `Trace` logs to the log stream. At most `per_minute` messages are added,
+    /// so dlog does not flood the stream while the sinks are unreachable.
+    Stream { per_minute: u32 },
+}
+
+impl Diagnostics {
+    pub fn callback(callback: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(callback))
+    }
+}
+
+struct Window {
+    started: Instant,
+    reported: u32,
+    suppressed: u32,
+}
+
+pub struct Reporter {
+    diagnostics: Diagnostics,
+    log_sender: flume::Sender<Signal>,
+    window: Mutex<Window>,
+}
+
+impl Reporter {
+    pub fn new(diagnostics: Diagnostics, log_sender: flume::Sender<Signal>) -> Self {
+        Self {
+            diagnostics,
+            log_sender,
+            window: Mutex::new(Window {
+                started: Instant::now(),
+                reported: 0,
+                suppressed: 0,
+            }),
+        }
+    }
+
+    pub fn report(&self, message: impl Into<String>) {
+        let message = message.into();
+        match &self.diagnostics {
+            Diagnostics::Stderr => eprintln!("{}", message),
+            Diagnostics::Silent => (),
+            Diagnostics::Callback(callback) => callback(&message),
+            Diagnostics::Stream { per_minute } => self.stream(message, *per_minute),
+        }
+    }
+
+    fn stream(&self, message: String, per_minute: u32) {
+        let mut window = match self.window.lock() {
+            Ok(val) => val,
+            Err(err) => err.into_inner(),
+        };
+
+        if window.started.elapsed() >= STREAM_WINDOW {
+            if window.suppressed > 0 {
+                self.send(format!("[dlog] Suppressed {} diagnostic messages", window.suppressed));
+            }
+            *window = Window {
+                started: Instant::now(),
+                reported: 0,
+                suppressed: 0,
+            };
+        }
+
+        match window.reported < per_minute {
+            true => {
+                window.reported += 1;
+                self.send(message);
+            }
+            false => window.suppressed += 1,
+        }
+    }
+
+    fn send(&self, message: String) {
+        let _ = self.log_sender.send(Signal::Log(Log::new(Priority::Trace, message)));
+    }
+}
+
+impl std::fmt::Debug for Reporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reporter").finish()
+    }
+}
@@ -1,50 +1,247 @@
-use reqwest::header::HeaderValue;
+use reqwest::header::{HeaderValue, CONTENT_TYPE, DATE};
+use reqwest::StatusCode;
+use std::path::{Path, PathBuf};
+use std::cmp::min;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
 
-use crate::models::{Log, LogRequest, Priority};
+use crate::config::Config;
+use crate::diagnostics::Reporter;
+use crate::models::{Encoding, Health, Log, LogRequest, Priority};
+use crate::sinks::{async_trait, Sink, SinkError};
+
+#[derive(Debug)]
+pub struct ApiKey {
+    value: RwLock<HeaderValue>,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: reqwest::Url,
+    failures: AtomicU32,
+    retry_at: Mutex<Option<Instant>>,
+}
 
 #[derive(Debug)]
 pub struct HttpIngestor {
     client: reqwest::Client,
-    api_key: String,
+    api_key: Arc<ApiKey>,
+    endpoints: Vec<Endpoint>,
+    active: AtomicUsize,
+    health: RwLock<Option<Health>>,
+    clock_skew_threshold: Option<Duration>,
+    encoding: Encoding,
+    fallback: AtomicBool,
+    rejected: AtomicBool,
+    reporter: Arc<Reporter>,
 }
 
 const KEEP_ALIVE: std::time::Duration = std::time::Duration::from_secs(5);
+const API_KEY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+const ENDPOINT_BACKOFF: Duration = Duration::from_secs(5);
+const ENDPOINT_MAX_BACKOFF: Duration = Duration::from_secs(120);
+const NO_ENDPOINT: usize = usize::MAX;
+
+impl ApiKey {
+    pub fn new(api_key: &str) -> Result<Self, String> {
+        Ok(Self {
+            value: RwLock::new(Self::parse(api_key)?),
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        Self::new(&read_api_key(path)?)
+    }
+
+    pub fn get(&self) -> HeaderValue {
+        match self.value.read() {
+            Ok(value) => value.clone(),
+            Err(err) => err.into_inner().clone(),
+        }
+    }
+
+    pub fn set(&self, api_key: &str) -> Result<(), String> {
+        let value = Self::parse(api_key)?;
+        match self.value.write() {
+            Ok(mut current) => *current = value,
+            Err(err) => *err.into_inner() = value,
+        }
+        Ok(())
+    }
+
+    pub async fn watch(&self, path: PathBuf, reporter: &Reporter) {
+        let mut current = read_api_key(&path).ok();
+        loop {
+            tokio::time::sleep(API_KEY_POLL_INTERVAL).await;
+            match read_api_key(&path) {
+                Ok(api_key) if current.as_ref() != Some(&api_key) => {
+                    if let Err(err) = self.set(&api_key) {
+                        reporter.report(format!("[dlog::ingest] Ignoring API_KEY from {:?}: {}", path, err));
+                    }
+                    current = Some(api_key);
+                }
+                Err(err) => reporter.report(format!("[dlog::ingest] Failed to reload API_KEY: {}", err)),
+                _ => (),
+            }
+        }
+    }
+
+    fn parse(api_key: &str) -> Result<HeaderValue, String> {
+        let api_key = api_key.trim();
+        if api_key.is_empty() {
+            return Err(String::from("[dlog::logger] Please configure dlog with a valid API_KEY"));
+        } else if !api_key.chars().all(|c| c.is_ascii_graphic()) {
+            return Err(String::from("[dlog::logger] The API_KEY contains invalid characters"));
+        }
+
+        let mut value = HeaderValue::from_str(api_key)
+            .map_err(|_| String::from("[dlog::logger] The API_KEY contains invalid characters"))?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+}
 
 impl HttpIngestor {
-    pub fn new(api_key: String) -> Result<Self, String> {
-        let client = reqwest::ClientBuilder::new()
+    pub fn new(api_key: Arc<ApiKey>, config: &Config, reporter: Arc<Reporter>) -> Result<Self, String> {
+        let mut builder = reqwest::ClientBuilder::new()
             .connection_verbose(false)
             .tcp_keepalive(KEEP_ALIVE)
             .use_rustls_tls()
-            .https_only(true)
+            .https_only(true);
+
+        if !config.env_proxy {
+            builder = builder.no_proxy();
+        }
+
+        if let Some(url) = &config.proxy {
+            let proxy = reqwest::Proxy::all(url).map_err(|err| format!("Invalid proxy {}: {}", url, err))?;
+            builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_env()));
+        }
+
+        for path in &config.root_certificates {
+            let certificate = reqwest::Certificate::from_pem(&read_pem(path)?)
+                .map_err(|err| format!("Invalid root certificate {:?}: {}", path, err))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        if let Some((certificate, key)) = &config.client_certificate {
+            let mut pem = read_pem(certificate)?;
+            pem.push(b'\n');
+            pem.append(&mut read_pem(key)?);
+            let identity = reqwest::Identity::from_pem(&pem)
+                .map_err(|err| format!("Invalid client certificate {:?}: {}", certificate, err))?;
+            builder = builder.identity(identity);
+        }
+
+        let client = builder
             .build()
             .map_err(|err| format!("Failed to build reqwest client: {}", err))?;
 
+        if config.endpoints.is_empty() {
+            return Err(String::from("[dlog::logger] At least one ingest endpoint is required"));
+        }
+
+        let mut endpoints = Vec::with_capacity(config.endpoints.len());
+        for url in &config.endpoints {
+            endpoints.push(Endpoint {
+                url: reqwest::Url::parse(url).map_err(|err| format!("Invalid ingest endpoint {}: {}", url, err))?,
+                failures: AtomicU32::new(0),
+                retry_at: Mutex::new(None),
+            });
+        }
+
         Ok(Self {
             client,
             api_key,
+            endpoints,
+            active: AtomicUsize::new(NO_ENDPOINT),
+            health: RwLock::new(None),
+            clock_skew_threshold: config.clock_skew_threshold,
+            encoding: config.encoding,
+            fallback: AtomicBool::new(false),
+            rejected: AtomicBool::new(false),
+            reporter,
         })
     }
 
+    pub fn api_key(&self) -> HeaderValue {
+        self.api_key.get()
+    }
+
+    pub fn set_api_key(&self, api_key: &str) -> Result<(), String> {
+        self.api_key.set(api_key)
+    }
+
+    pub async fn watch_api_key(&self, path: PathBuf) {
+        self.api_key.watch(path, &self.reporter).await
+    }
+
+    /// Returns whether the last response of the dlog cloud rejected the API_KEY
+    pub fn is_rejected(&self) -> bool {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Returns the reporter for problems of the ingest connection
+    pub fn reporter(&self) -> &Reporter {
+        &self.reporter
+    }
+
+    pub fn health(&self) -> Option<Health> {
+        match self.health.read() {
+            Ok(health) => *health,
+            Err(err) => *err.into_inner(),
+        }
+    }
+
+    /// Returns the offset which must be added to local timestamps if the measured clock skew exceeds
+    /// the configured threshold
+    pub fn timestamp_correction(&self) -> Option<time::Duration> {
+        let threshold = self.clock_skew_threshold?;
+        self.health()
+            .and_then(|health| health.clock_skew)
+            .filter(|skew| skew.unsigned_abs() > threshold)
+    }
+
+    /// Returns the WebSocket url of the active endpoint, or of the primary one if none is active yet
+    pub fn stream_url(&self) -> Result<String, String> {
+        let mut url = self.active_endpoint().unwrap_or(&self.endpoints[0].url).clone();
+        url.set_scheme("wss")
+            .map_err(|_| format!("Cannot stream to ingest endpoint {}", url))?;
+        url.set_path("stream");
+        Ok(url.to_string())
+    }
+
+    /// Returns the endpoint which accepted the last request
+    pub fn active_endpoint(&self) -> Option<&reqwest::Url> {
+        self.endpoints
+            .get(self.active.load(Ordering::Relaxed))
+            .map(|endpoint| &endpoint.url)
+    }
+
     pub async fn has_valid_api_key(&self) -> bool {
-        if let Ok(res) = self.send_async(LogRequest::new(&[])).await {
-            if res.text().await.unwrap_or_default().contains("Invalid API_KEY") {
-                return false;
-            }
+        match self.send_async(None).await {
+            Ok(res) => !is_unauthorized(res.status()),
+            Err(_) => true,
         }
-        true
     }
 
     pub async fn check(&self) -> bool {
-        matches!(self.send_async(LogRequest::new(&[])).await, Ok(res) if res.status().is_success())
+        matches!(self.send_async(None).await, Ok(res) if res.status().is_success())
     }
 
     pub async fn log_async(&self, logs: &[Log]) -> Result<(), Log> {
-        match self.send_async(LogRequest::new(logs)).await {
+        match self.send_async(Some(&LogRequest::new(logs))).await {
             Err(err) => Err(Log::new(
                 Priority::Trace,
                 format!("[dlog] API connection error: {}", err),
             )),
+            Ok(val) if is_unauthorized(val.status()) => Err(Log::new(
+                Priority::Trace,
+                String::from("[dlog] Log ingestion failed: The API_KEY was rejected"),
+            )),
             Ok(val) if !val.status().is_success() => Err(Log::new(
                 Priority::Trace,
                 format!("[dlog] Log ingestion failed: {}", val.text().await.unwrap_or_default()),
@@ -53,13 +250,175 @@ impl HttpIngestor {
         }
     }
 
-    async fn send_async<T: serde::Serialize + Sized>(&self, request: T) -> Result<reqwest::Response, reqwest::Error> {
+    fn encoding(&self) -> Encoding {
+        match self.fallback.load(Ordering::Relaxed) {
+            true => Encoding::Json,
+            false => self.encoding,
+        }
+    }
+
+    /// Sends the logs, or probes the health endpoint if there are none, to the first available
+    /// endpoint
+    async fn send_async(&self, request: Option<&LogRequest<'_>>) -> Result<reqwest::Response, String> {
+        let mut error = String::from("All ingest endpoints are backing off");
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            if !endpoint.is_available() {
+                continue;
+            }
+
+            match self.send_to(&endpoint.url, request).await {
+                Ok(res) if !res.status().is_server_error() => {
+                    endpoint.succeeded();
+                    self.rejected.store(is_unauthorized(res.status()), Ordering::Relaxed);
+                    let previous = self.active.swap(index, Ordering::Relaxed);
+                    if previous != index && previous != NO_ENDPOINT {
+                        self.reporter
+                            .report(format!("[dlog::ingest] Switched to ingest endpoint {}", endpoint.url));
+                    }
+                    return Ok(res);
+                }
+                Ok(res) => {
+                    endpoint.failed();
+                    error = format!("{} responded with {}", endpoint.url, res.status());
+                }
+                Err(err) => {
+                    endpoint.failed();
+                    error = err;
+                }
+            }
+        }
+        Err(error)
+    }
+
+    async fn send_to(&self, url: &reqwest::Url, request: Option<&LogRequest<'_>>) -> Result<reqwest::Response, String> {
+        let (started, sent_at) = (Instant::now(), OffsetDateTime::now_utc());
+        let encoding = self.encoding();
+        let res = match request {
+            Some(request) => self.post(url, request, encoding).await?,
+            None => self.probe(url).await?,
+        };
+        self.record(&res, sent_at, started.elapsed());
+
+        // Servers which do not support the configured encoding respond with 415 => Fall back to JSON
+        match request {
+            Some(request) if res.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE && encoding != Encoding::Json => {
+                self.reporter.report(format!(
+                    "[dlog::ingest] {} is not supported by the server, falling back to JSON",
+                    encoding
+                ));
+                self.fallback.store(true, Ordering::Relaxed);
+                self.post(url, request, Encoding::Json).await
+            }
+            _ => Ok(res),
+        }
+    }
+
+    fn record(&self, res: &reqwest::Response, sent_at: OffsetDateTime, latency: Duration) {
+        let server_time = res
+            .headers()
+            .get(DATE)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| OffsetDateTime::parse(val, &Rfc2822).ok());
+
+        let health = Health {
+            latency,
+            server_time,
+            clock_skew: server_time.map(|val| val - (sent_at + latency / 2)),
+            measured_at: OffsetDateTime::now_utc(),
+        };
+
+        match self.health.write() {
+            Ok(mut current) => *current = Some(health),
+            Err(err) => *err.into_inner() = Some(health),
+        }
+    }
+
+    /// Requests the health endpoint, which neither parses nor stores anything, so its latency and
+    /// `Date` header are a cheap measurement of the connection
+    async fn probe(&self, url: &reqwest::Url) -> Result<reqwest::Response, String> {
+        let mut url = url.clone();
+        url.set_path("health");
+        self.client
+            .get(url)
+            .header("API_KEY", self.api_key.get())
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .map_err(|err| err.to_string())
+    }
+
+    async fn post(
+        &self,
+        url: &reqwest::Url,
+        request: &LogRequest<'_>,
+        encoding: Encoding,
+    ) -> Result<reqwest::Response, String> {
+        let body = encoding
+            .encode(request)
+            .map_err(|err| format!("Failed to encode request as {}: {}", encoding, err))?;
+
         self.client
-            .post("https://log.dlog.sh")
-            .json(&request)
-            .header("API_KEY", HeaderValue::from_str(&self.api_key).unwrap())
+            .post(url.clone())
+            .body(body)
+            .header(CONTENT_TYPE, HeaderValue::from_static(encoding.content_type()))
+            .header("API_KEY", self.api_key.get())
             .timeout(std::time::Duration::from_secs(5))
             .send()
             .await
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl Endpoint {
+    fn is_available(&self) -> bool {
+        match self.retry_at.lock() {
+            Ok(retry_at) => !matches!(*retry_at, Some(val) if Instant::now() < val),
+            Err(_) => true,
+        }
+    }
+
+    fn succeeded(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+        if let Ok(mut retry_at) = self.retry_at.lock() {
+            *retry_at = None;
+        }
+    }
+
+    fn failed(&self) {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed).min(5);
+        let backoff = min(ENDPOINT_BACKOFF * 2u32.pow(failures), ENDPOINT_MAX_BACKOFF);
+        if let Ok(mut retry_at) = self.retry_at.lock() {
+            *retry_at = Some(Instant::now() + backoff);
+        }
+    }
+}
+
+fn is_unauthorized(status: StatusCode) -> bool {
+    status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN
+}
+
+fn read_api_key(path: &Path) -> Result<String, String> {
+    match std::fs::read_to_string(path) {
+        Ok(api_key) => Ok(api_key.trim().to_owned()),
+        Err(err) => Err(format!("[dlog::logger] Cannot read API_KEY from {:?}: {}", path, err)),
+    }
+}
+
+pub(crate) fn read_pem(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|err| format!("Cannot read {:?}: {}", path, err))
+}
+
+#[async_trait]
+impl Sink for HttpIngestor {
+    fn name(&self) -> &str {
+        "cloud"
+    }
+
+    async fn check(&self) -> bool {
+        HttpIngestor::check(self).await
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        Ok(HttpIngestor::log_async(self, logs).await?)
     }
 }
@@ -1,29 +1,63 @@
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 mod backlog;
+pub mod config;
+pub mod diagnostics;
 mod ingest;
 pub mod models;
+mod shutdown;
+pub mod sinks;
+pub mod stats;
+mod status;
+mod stream;
+mod throttle;
 pub mod transforms;
 mod worker;
 
-use crate::models::{Log, Priority};
+use crate::config::Config;
+use crate::diagnostics::Reporter;
+use crate::ingest::HttpIngestor;
+use crate::models::{Confirmation, FlushReport, Health, Log, Priority, Status};
+use crate::shutdown::Shutdown;
+use crate::stats::{Counters, Stats};
+use crate::status::Monitor;
 use crate::transforms::Transforms;
 use crate::worker::{Signal, Worker};
 
 const FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+pub(crate) const FLUSH_GRACE: std::time::Duration = std::time::Duration::from_millis(500);
 
 #[derive(Debug)]
 pub struct Logger {
     signal_sender: flume::Sender<Signal>,
-    flush_receiver: flume::Receiver<()>,
+    shutdown: Arc<Shutdown>,
+    ingest: Option<Arc<HttpIngestor>>,
+    counters: Arc<Counters>,
+    monitor: Arc<Monitor>,
+    reporter: Arc<Reporter>,
     handle: RwLock<Option<tokio::runtime::Runtime>>,
 }
 
 impl Logger {
-    pub fn new(api_key: String, transforms: Transforms) -> Result<Self, String> {
-        let (mut worker, mut backlog) = Worker::new(api_key, transforms)?;
+    pub fn new(api_key: String, transforms: Transforms, config: Config) -> Result<Self, String> {
+        let api_key_file = config.api_key_file.clone();
+        let (drain_deadline, signal_handler) = (config.drain_deadline, config.signal_handler);
+        let (mut worker, backlogs) = Worker::new(api_key, transforms, config)?;
         let (signal_sender, flush_receiver) = (worker.signal_sender.clone(), worker.flush_receiver.clone());
+        let ingest = worker.ingest.clone();
+        let counters = worker.counters.clone();
+        let monitor = worker.monitor.clone();
+        let reporter = worker.reporter.clone();
+        let shutdown = Arc::new(Shutdown::new(
+            signal_sender.clone(),
+            flush_receiver.clone(),
+            monitor.clone(),
+            reporter.clone(),
+            drain_deadline,
+        ));
 
+        let worker_ingest = ingest.clone();
+        let worker_shutdown = shutdown.clone();
         let (valid_tx, valid_rx) = flume::bounded(1);
         let runtime = tokio::runtime::Runtime::new().unwrap();
         runtime.spawn(async move {
@@ -34,11 +68,18 @@ impl Logger {
                 )
             }
 
-            let _ = futures::future::try_join_all(vec![
-                tokio::task::spawn(async move { worker.start().await }),
-                tokio::task::spawn(async move { backlog.start().await }),
-            ])
-            .await;
+            let mut tasks = vec![tokio::task::spawn(async move { worker.start().await })];
+            if let (Some(ingest), Some(path)) = (worker_ingest, api_key_file) {
+                tasks.push(tokio::task::spawn(async move { ingest.watch_api_key(path).await }));
+            }
+            if signal_handler {
+                tasks.push(tokio::task::spawn(async move { worker_shutdown.on_signal().await }));
+            }
+            for mut backlog in backlogs {
+                tasks.push(tokio::task::spawn(async move { backlog.start().await }));
+            }
+
+            let _ = futures::future::try_join_all(tasks).await;
         });
 
         match valid_rx.recv() {
@@ -60,57 +101,164 @@ impl Logger {
 
         // Wait for first flush signal => Ready to be used
         if let Err(err) = flush_receiver.recv_timeout(std::time::Duration::from_secs(3)) {
-            eprintln!("[dlog::logger] Failed to receive ready signal: {}", err);
+            reporter.report(format!("[dlog::logger] Failed to receive ready signal: {}", err));
         }
 
         Ok(Self {
             signal_sender,
-            flush_receiver,
+            shutdown,
+            ingest,
+            counters,
+            monitor,
+            reporter,
             handle: RwLock::new(Some(runtime)),
         })
     }
 
     pub fn log(&self, priority: Priority, message: String) -> Result<(), String> {
+        self.counters.received();
         match self.signal_sender.send(Signal::Log(Log::new(priority, message))) {
-            Err(err) => Err(format!("[dlog::logger] Failed to move log to sender: {}", err)),
+            Err(err) => {
+                self.counters.dropped(1);
+                Err(format!("[dlog::logger] Failed to move log to sender: {}", err))
+            }
             _ => Ok(()),
         }
     }
 
+    /// Logs the message and blocks until every sink accepted it or it was written to the backlog on
+    /// disk. Use this for events which must not be lost, e.g. payments or permission changes.
+    pub fn log_and_confirm(
+        &self,
+        priority: Priority,
+        message: String,
+        timeout: std::time::Duration,
+    ) -> Result<Confirmation, String> {
+        self.counters.received();
+        let expires = std::time::Instant::now() + timeout;
+        let (reply, response) = flume::bounded(1);
+        if let Err(err) = self
+            .signal_sender
+            .send_deadline(Signal::Confirm(Log::new(priority, message), reply), expires)
+        {
+            self.counters.dropped(1);
+            return Err(format!("[dlog::logger] Failed to move log to sender: {}", err));
+        }
+
+        match response.recv_deadline(expires) {
+            Err(flume::RecvTimeoutError::Timeout) => Err(format!(
+                "[dlog::logger] The log was not confirmed within {} ms",
+                timeout.as_millis()
+            )),
+            Err(flume::RecvTimeoutError::Disconnected) => {
+                Err("[dlog::logger] Failed to receive thread signal".to_string())
+            }
+            Ok(res) => res,
+        }
+    }
+
+    /// Returns the counters collected since the logger was created
+    pub fn stats(&self) -> Stats {
+        self.counters.snapshot()
+    }
+
+    /// Rotates the API_KEY used for all subsequent requests to the dlog cloud.
+    pub fn set_api_key(&self, api_key: &str) -> Result<(), String> {
+        match &self.ingest {
+            Some(ingest) => ingest.set_api_key(api_key),
+            None => Err(String::from("[dlog::logger] The dlog cloud is disabled")),
+        }
+    }
+
+    /// Returns whether logs are currently delivered, written to the backlog, rejected by the dlog
+    /// cloud or whether the logger is shutting down
+    pub fn status(&self) -> Status {
+        self.monitor.get()
+    }
+
+    /// Registers a callback which is invoked on the background thread whenever the status changes.
+    /// The callback must not register further callbacks.
+    pub fn on_status_change(&self, callback: impl Fn(Status) + Send + Sync + 'static) {
+        self.monitor.subscribe(callback);
+    }
+
+    /// Returns the latency, server time and clock skew measured by the last request to the dlog cloud
+    pub fn health(&self) -> Option<Health> {
+        self.ingest.as_ref().and_then(|ingest| ingest.health())
+    }
+
+    /// Returns the ingest endpoint which accepted the last request, if any
+    pub fn active_endpoint(&self) -> Option<String> {
+        self.ingest
+            .as_ref()
+            .and_then(|ingest| ingest.active_endpoint())
+            .map(|url| url.to_string())
+    }
+
+    /// Sends all queued logs and retries the backlogs. A flush which does not finish in time is
+    /// reported but not treated as an error, use `flush_with_deadline` to handle it.
     pub fn flush(&self) -> Result<(), String> {
-        if let Err(err) = self.signal_sender.send_timeout(Signal::Flush, FLUSH_TIMEOUT) {
+        if self.request_flush(FLUSH_TIMEOUT)?.is_none() {
+            self.reporter.report(format!(
+                "[dlog::logger] The flush did not finish within {} ms",
+                FLUSH_TIMEOUT.as_millis()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sends all queued logs and retries the backlogs, waiting at most `deadline`. The report tells
+    /// whether all logs were delivered, e.g. before a serverless function is frozen. A chunk which is
+    /// already being sent is awaited for a short grace period, after which an error is returned.
+    pub fn flush_with_deadline(&self, deadline: std::time::Duration) -> Result<FlushReport, String> {
+        self.request_flush(deadline)?.ok_or_else(|| {
+            format!(
+                "[dlog::logger] The flush did not finish within {} ms",
+                deadline.as_millis()
+            )
+        })
+    }
+
+    /// Returns `None` if the background thread did not report back in time
+    fn request_flush(&self, deadline: std::time::Duration) -> Result<Option<FlushReport>, String> {
+        let expires = std::time::Instant::now() + deadline;
+        let (reply, response) = flume::bounded(1);
+        if let Err(err) = self.signal_sender.send_deadline(Signal::Flush(expires, reply), expires) {
             return Err(format!("[dlog::logger] Failed to send thread signal: {}", err));
         }
 
-        match self.flush_receiver.recv_timeout(FLUSH_TIMEOUT) {
+        match response.recv_deadline(expires + FLUSH_GRACE) {
             Err(flume::RecvTimeoutError::Disconnected) => {
                 Err("[dlog::logger] Failed to receive thread signal".to_string())
             }
-            _ => Ok(()),
+            Err(flume::RecvTimeoutError::Timeout) => Ok(None),
+            Ok(report) => Ok(Some(report)),
         }
     }
 
+    /// Sends the remaining logs and stops the background thread. Repeated calls have no effect.
     pub fn clean_up(&self) {
-        match self.signal_sender.send_timeout(Signal::Exit, FLUSH_TIMEOUT) {
-            Err(err) => println!(
-                "[dlog::logger] Could not send exit signal, some logs might be lost: {}",
-                err
-            ),
-            Ok(_) => {
-                if let Err(err) = self.flush_receiver.recv_timeout(FLUSH_TIMEOUT) {
-                    eprintln!("[dlog::logger] Failed to exit signal response: {}", err);
-                }
-            }
+        if !self.shutdown.drain() {
+            return;
         }
 
         let mut write = match self.handle.write() {
             Err(err) => {
-                println!("[dlog::logger] Failed to get write lock during cleanup: {}", err);
+                self.reporter
+                    .report(format!("[dlog::logger] Failed to get write lock during cleanup: {}", err));
                 return;
             }
             Ok(val) => val,
         };
 
-        let _ = write.take();
+        if let Some(runtime) = write.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        self.clean_up();
     }
 }
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Priority {
     Critical,
     Error,
@@ -12,6 +12,20 @@ pub enum Priority {
     Trace,
 }
 
+impl Priority {
+    /// Returns a rank which is higher for more severe priorities
+    pub(crate) fn severity(self) -> u8 {
+        match self {
+            Self::Critical => 5,
+            Self::Error => 4,
+            Self::Warning => 3,
+            Self::Info => 2,
+            Self::Debug => 1,
+            Self::Trace => 0,
+        }
+    }
+}
+
 impl Display for Priority {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -25,7 +39,7 @@ impl Display for Priority {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Log {
     #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
@@ -45,6 +59,77 @@ impl Log {
     }
 }
 
+/// The delivery state of the logger as seen by the background worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Connected,
+    Backlogging,
+    AuthFailed,
+    ShuttingDown,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connected => write!(f, "connected"),
+            Self::Backlogging => write!(f, "backlogging"),
+            Self::AuthFailed => write!(f, "auth_failed"),
+            Self::ShuttingDown => write!(f, "shutting_down"),
+        }
+    }
+}
+
+/// Controls when logs of a priority are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Queues the log until the next batch is sent
+    Batch,
+    /// Sends the queued logs as soon as the log arrives
+    Immediate,
+    /// Sends the queued logs as soon as the log arrives and waits until failed logs are written to
+    /// the backlog on disk
+    Durable,
+}
+
+/// Describes how a log passed to `Logger::log_and_confirm` was secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// All sinks accepted the log
+    Delivered,
+    /// At least one sink was unavailable and the log was written to its backlog on disk
+    Backlogged,
+    /// A transform dropped the log
+    Filtered,
+}
+
+/// Describes the outcome of a flush. `delivered` counts every log accepted by a sink, so a log sent
+/// to two sinks is counted twice. `backlogged` counts the logs which are still waiting in the
+/// backlogs after the flush and `pending` the logs which were not sent before the deadline expired.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlushReport {
+    pub delivered: usize,
+    pub backlogged: usize,
+    pub pending: usize,
+    pub deadline_expired: bool,
+}
+
+impl FlushReport {
+    /// Returns whether all logs were delivered before the deadline expired
+    pub fn is_complete(&self) -> bool {
+        !self.deadline_expired && self.backlogged == 0 && self.pending == 0
+    }
+}
+
+/// The result of the last request to the dlog cloud. `clock_skew` is the difference between the
+/// server `Date` header and the local clock, which has a resolution of one second.
+#[derive(Debug, Clone, Copy)]
+pub struct Health {
+    pub latency: std::time::Duration,
+    pub server_time: Option<OffsetDateTime>,
+    pub clock_skew: Option<time::Duration>,
+    pub measured_at: OffsetDateTime,
+}
+
 #[derive(Serialize)]
 pub struct LogRequest<'a> {
     pub logs: &'a [Log],
@@ -55,3 +140,45 @@ impl<'a> LogRequest<'a> {
         Self { logs }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    Ndjson,
+    MessagePack,
+}
+
+impl Encoding {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Ndjson => "application/x-ndjson",
+            Self::MessagePack => "application/msgpack",
+        }
+    }
+
+    pub fn encode(&self, request: &LogRequest) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Json => serde_json::to_vec(request).map_err(|err| err.to_string()),
+            Self::Ndjson => {
+                let mut body = Vec::new();
+                for log in request.logs {
+                    serde_json::to_writer(&mut body, log).map_err(|err| err.to_string())?;
+                    body.push(b'\n');
+                }
+                Ok(body)
+            }
+            Self::MessagePack => rmp_serde::to_vec_named(request).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json => write!(f, "JSON"),
+            Self::Ndjson => write!(f, "NDJSON"),
+            Self::MessagePack => write!(f, "MessagePack"),
+        }
+    }
+}
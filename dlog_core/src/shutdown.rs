@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::diagnostics::Reporter;
+use crate::models::Status;
+use crate::status::Monitor;
+use crate::worker::Signal;
+use crate::FLUSH_GRACE;
+
+/// Drains the worker exactly once, either when the logger is cleaned up or dropped or when the
+/// process receives SIGTERM or SIGINT.
+#[derive(Debug)]
+pub struct Shutdown {
+    signal_sender: flume::Sender<Signal>,
+    flush_receiver: flume::Receiver<()>,
+    monitor: Arc<Monitor>,
+    reporter: Arc<Reporter>,
+    deadline: Duration,
+    closed: AtomicBool,
+}
+
+impl Shutdown {
+    pub fn new(
+        signal_sender: flume::Sender<Signal>,
+        flush_receiver: flume::Receiver<()>,
+        monitor: Arc<Monitor>,
+        reporter: Arc<Reporter>,
+        deadline: Duration,
+    ) -> Self {
+        Self {
+            signal_sender,
+            flush_receiver,
+            monitor,
+            reporter,
+            deadline,
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns whether this call performed the shutdown
+    fn begin(&self) -> bool {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        self.monitor.set(Status::ShuttingDown);
+        true
+    }
+
+    pub fn drain(&self) -> bool {
+        if !self.begin() {
+            return false;
+        }
+
+        // The worker stops sending at the deadline and only needs the grace period to persist the rest
+        let deadline = Instant::now() + self.deadline;
+        match self.signal_sender.send_deadline(Signal::Exit(deadline), deadline) {
+            Err(err) => self.reporter.report(format!(
+                "[dlog::logger] Could not send exit signal, some logs might be lost: {}",
+                err
+            )),
+            Ok(_) => {
+                if let Err(err) = self.flush_receiver.recv_deadline(deadline + FLUSH_GRACE) {
+                    self.reporter
+                        .report(format!("[dlog::logger] Failed to exit signal response: {}", err));
+                }
+            }
+        }
+        true
+    }
+
+    async fn drain_async(&self) {
+        if !self.begin() {
+            return;
+        }
+
+        let deadline = Instant::now() + self.deadline;
+        if let Err(err) = self.signal_sender.send_async(Signal::Exit(deadline)).await {
+            self.reporter.report(format!(
+                "[dlog::logger] Could not send exit signal, some logs might be lost: {}",
+                err
+            ));
+        } else if tokio::time::timeout(self.deadline + FLUSH_GRACE, self.flush_receiver.recv_async())
+            .await
+            .is_err()
+        {
+            self.reporter
+                .report("[dlog::logger] Exit signal response timed out, some logs might be lost");
+        }
+    }
+
+    pub async fn on_signal(self: Arc<Self>) {
+        let code = match wait_for_signal().await {
+            Ok(val) => val,
+            Err(err) => {
+                self.reporter
+                    .report(format!("[dlog::logger] Cannot install signal handler: {}", err));
+                return;
+            }
+        };
+
+        self.drain_async().await;
+        std::process::exit(code);
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> std::io::Result<i32> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => Ok(143),
+        _ = interrupt.recv() => Ok(130),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> std::io::Result<i32> {
+    tokio::signal::ctrl_c().await.map(|_| 130)
+}
@@ -0,0 +1,195 @@
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use serde::Deserialize;
+use serde_json::json;
+use std::fmt::Write;
+
+use crate::models::{Log, Priority};
+use crate::sinks::{async_trait, client, sanitize, Sink, SinkError};
+
+pub struct ElasticSinkOptions {
+    pub url: String,
+    pub index: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl ElasticSinkOptions {
+    /// The `index` may contain the date patterns `%Y`, `%m`, `%d` and `%H` which are replaced
+    /// with the UTC timestamp of each log, e.g. `logs-%Y.%m.%d`.
+    pub fn new(url: impl Into<String>, index: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            index: index.into(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BulkResponse {
+    errors: bool,
+    items: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Indexes logs into Elasticsearch or OpenSearch via the `_bulk` API. Items rejected with a
+/// retryable status (`429` or `5xx`) are retried from the backlog, all other rejected items are dropped.
+pub struct ElasticSink {
+    name: String,
+    client: reqwest::Client,
+    base: reqwest::Url,
+    options: ElasticSinkOptions,
+}
+
+impl ElasticSink {
+    pub fn new(options: ElasticSinkOptions) -> Result<Self, String> {
+        let base = reqwest::Url::parse(&options.url)
+            .map_err(|err| format!("[dlog::sinks] Invalid Elasticsearch url {}: {}", options.url, err))?;
+
+        if options.index.is_empty() {
+            return Err(String::from("[dlog::sinks] The Elasticsearch index must not be empty"));
+        }
+
+        Ok(Self {
+            name: format!("elastic_{}", sanitize(base.host_str().unwrap_or_default())),
+            client: client()?,
+            base,
+            options,
+        })
+    }
+
+    fn index(&self, log: &Log) -> String {
+        let utc = log.timestamp.to_offset(time::UtcOffset::UTC);
+        self.options
+            .index
+            .replace("%Y", &format!("{:04}", utc.year()))
+            .replace("%m", &format!("{:02}", utc.month() as u8))
+            .replace("%d", &format!("{:02}", utc.day()))
+            .replace("%H", &format!("{:02}", utc.hour()))
+    }
+
+    fn encode(&self, logs: &[Log]) -> String {
+        let mut body = String::new();
+        for log in logs {
+            let action = json!({ "index": { "_index": self.index(log) } });
+            let _ = writeln!(body, "{}", action);
+            let _ = writeln!(body, "{}", serde_json::to_string(log).unwrap_or_default());
+        }
+        body
+    }
+
+    fn request(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.options.username {
+            Some(username) => builder.basic_auth(username, self.options.password.as_ref()),
+            None => builder,
+        }
+    }
+
+    async fn bulk(&self, logs: &[Log]) -> Result<BulkResponse, String> {
+        let url = self
+            .base
+            .join("_bulk")
+            .map_err(|err| format!("[dlog::sinks] Invalid Elasticsearch url: {}", err))?;
+
+        let res = self
+            .request(self.client.post(url))
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"))
+            .body(self.encode(logs))
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .map_err(|err| format!("[dlog] Elasticsearch connection error: {}", err))?;
+
+        if !res.status().is_success() {
+            return Err(format!(
+                "[dlog] Elasticsearch ingestion failed: {}",
+                res.text().await.unwrap_or_default()
+            ));
+        }
+
+        res.json::<BulkResponse>()
+            .await
+            .map_err(|err| format!("[dlog] Invalid Elasticsearch bulk response: {}", err))
+    }
+}
+
+#[async_trait]
+impl Sink for ElasticSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> bool {
+        matches!(self.request(self.client.get(self.base.clone())).send().await, Ok(res) if res.status().is_success())
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        let response = self.bulk(logs).await.map_err(|err| Log::new(Priority::Trace, err))?;
+        if !response.errors {
+            return Ok(());
+        }
+
+        Err(bulk_error(&response))
+    }
+}
+
+fn bulk_error(response: &BulkResponse) -> SinkError {
+    let (mut failed, mut rejected) = (Vec::new(), Vec::new());
+    for (index, item) in response.items.iter().enumerate() {
+        let status = item
+            .values()
+            .next()
+            .and_then(|val| val.get("status"))
+            .and_then(|val| val.as_u64())
+            .unwrap_or_default();
+
+        match status {
+            200..=299 => (),
+            429 | 500..=599 => failed.push(index),
+            _ => rejected.push(index),
+        }
+    }
+
+    let message = format!(
+        "[dlog] Elasticsearch rejected {} logs and will retry {} logs",
+        rejected.len(),
+        failed.len()
+    );
+    SinkError::partial(Log::new(Priority::Trace, message), failed).with_rejected(rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(statuses: &[u64]) -> BulkResponse {
+        let items = statuses
+            .iter()
+            .map(|status| json!({ "index": { "status": status } }).as_object().unwrap().clone())
+            .collect();
+        BulkResponse { errors: true, items }
+    }
+
+    fn logs(count: usize) -> Vec<Log> {
+        (0..count)
+            .map(|index| Log::new(Priority::Info, index.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn counts_all_bad_requests_as_rejected() {
+        let (retry, rejected, _) = bulk_error(&response(&[400, 400, 400])).split(logs(3));
+        assert!(retry.is_empty());
+        assert_eq!(rejected, 3);
+    }
+
+    #[test]
+    fn retries_only_retryable_items() {
+        let (retry, rejected, _) = bulk_error(&response(&[201, 429, 400, 503])).split(logs(4));
+        assert_eq!(
+            retry.iter().map(|log| log.text.as_str()).collect::<Vec<_>>(),
+            ["1", "3"]
+        );
+        assert_eq!(rejected, 1);
+    }
+}
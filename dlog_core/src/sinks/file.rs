@@ -0,0 +1,334 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::models::{Log, Priority};
+use crate::sinks::{async_trait, Sink, SinkError};
+use crate::transforms::console;
+
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_ARCHIVES: usize = 5;
+
+pub enum FileFormat {
+    Json,
+    Console,
+}
+
+pub enum Rotation {
+    Never,
+    Size(u64),
+    Interval(Duration),
+}
+
+pub struct FileSinkOptions {
+    pub path: PathBuf,
+    pub format: FileFormat,
+    pub rotation: Rotation,
+    pub max_archives: usize,
+    pub compress: bool,
+}
+
+impl FileSinkOptions {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: FileFormat::Json,
+            rotation: Rotation::Size(DEFAULT_MAX_SIZE),
+            max_archives: DEFAULT_MAX_ARCHIVES,
+            compress: false,
+        }
+    }
+}
+
+struct FileState {
+    file: File,
+    size: u64,
+    opened: SystemTime,
+}
+
+/// Writes logs to a local file and rotates it according to the configured [`Rotation`].
+///
+/// On unix systems the file is reopened on `SIGHUP` so that it can be used together with an
+/// external logrotate. Note that this replaces the default `SIGHUP` action of the process.
+pub struct FileSink {
+    name: String,
+    writer: Arc<FileWriter>,
+}
+
+/// Owns the file, so it can be written on a blocking thread instead of the runtime thread
+struct FileWriter {
+    options: FileSinkOptions,
+    state: Mutex<FileState>,
+    reopen: Arc<AtomicBool>,
+}
+
+impl FileSink {
+    pub fn new(options: FileSinkOptions) -> Result<Self, String> {
+        // The backlog is keyed by the name, so files with the same name in different directories
+        // must not share it
+        let absolute = match std::env::current_dir() {
+            Ok(dir) => dir.join(&options.path),
+            Err(_) => options.path.clone(),
+        };
+        let digest = Sha256::digest(absolute.to_string_lossy().as_bytes());
+        let hash = digest[..4]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let name = match options.path.file_stem() {
+            Some(stem) => format!("file_{}_{}", stem.to_string_lossy(), hash),
+            None => format!("file_{}", hash),
+        };
+
+        let state = FileWriter::open(&options)?;
+        let reopen = Arc::new(AtomicBool::new(false));
+
+        #[cfg(unix)]
+        if let Err(err) = signal_hook::flag::register(signal_hook::consts::SIGHUP, reopen.clone()) {
+            return Err(format!("[dlog::sinks] Failed to register SIGHUP handler: {}", err));
+        }
+
+        Ok(Self {
+            name,
+            writer: Arc::new(FileWriter {
+                options,
+                state: Mutex::new(state),
+                reopen,
+            }),
+        })
+    }
+}
+
+impl FileWriter {
+    fn open(options: &FileSinkOptions) -> Result<FileState, String> {
+        if let Some(parent) = options.path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| format!("[dlog::sinks] Cannot create directory {:?}: {}", parent, err))?;
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&options.path)
+            .map_err(|err| format!("[dlog::sinks] Cannot open {:?}: {}", options.path, err))?;
+
+        Ok(FileState {
+            size: file.metadata().map(|val| val.len()).unwrap_or_default(),
+            opened: SystemTime::now(),
+            file,
+        })
+    }
+
+    fn should_rotate(&self, state: &FileState) -> bool {
+        match self.options.rotation {
+            Rotation::Never => false,
+            Rotation::Size(max) => state.size >= max,
+            Rotation::Interval(interval) => state.opened.elapsed().unwrap_or_default() >= interval,
+        }
+    }
+
+    fn rotate(&self, state: &mut FileState) -> Result<(), String> {
+        let _ = state.file.flush();
+        if self.options.max_archives == 0 {
+            let _ = std::fs::remove_file(&self.options.path);
+        } else {
+            let _ = std::fs::remove_file(self.archive(self.options.max_archives));
+            for index in (1..self.options.max_archives).rev() {
+                let from = self.archive(index);
+                if from.exists() {
+                    let _ = std::fs::rename(&from, self.archive(index + 1));
+                }
+            }
+
+            let first = self.archive_path(1, false);
+            std::fs::rename(&self.options.path, &first)
+                .map_err(|err| format!("[dlog::sinks] Cannot rotate {:?}: {}", self.options.path, err))?;
+
+            if self.options.compress {
+                Self::compress(&first, &self.archive(1))?;
+            }
+        }
+
+        *state = Self::open(&self.options)?;
+        Ok(())
+    }
+
+    fn compress(from: &PathBuf, to: &PathBuf) -> Result<(), String> {
+        let mut source = File::open(from).map_err(|err| format!("[dlog::sinks] Cannot compress {:?}: {}", from, err))?;
+        let target = File::create(to).map_err(|err| format!("[dlog::sinks] Cannot compress {:?}: {}", from, err))?;
+
+        let mut encoder = GzEncoder::new(target, Compression::default());
+        std::io::copy(&mut source, &mut encoder)
+            .and_then(|_| encoder.finish())
+            .map_err(|err| format!("[dlog::sinks] Cannot compress {:?}: {}", from, err))?;
+
+        let _ = std::fs::remove_file(from);
+        Ok(())
+    }
+
+    fn archive(&self, index: usize) -> PathBuf {
+        self.archive_path(index, self.options.compress)
+    }
+
+    fn archive_path(&self, index: usize, compressed: bool) -> PathBuf {
+        let mut path = self.options.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        if compressed {
+            path.push(".gz");
+        }
+        PathBuf::from(path)
+    }
+
+    fn format(&self, log: &Log) -> String {
+        match self.options.format {
+            FileFormat::Json => serde_json::to_string(log).unwrap_or_default(),
+            FileFormat::Console => console::format(log),
+        }
+    }
+
+    /// Returns the index of the first log which was not written on failure
+    fn write(&self, logs: &[Log]) -> Result<(), (usize, String)> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|err| (0, format!("[dlog::sinks] Failed to lock file: {}", err)))?;
+
+        if self.reopen.swap(false, Ordering::Relaxed) {
+            *state = Self::open(&self.options).map_err(|err| (0, err))?;
+        }
+
+        for (index, log) in logs.iter().enumerate() {
+            if self.should_rotate(&state) {
+                self.rotate(&mut state).map_err(|err| (index, err))?;
+            }
+
+            let line = format!("{}\n", self.format(log));
+            state.file.write_all(line.as_bytes()).map_err(|err| {
+                (
+                    index,
+                    format!("[dlog::sinks] Cannot write to {:?}: {}", self.options.path, err),
+                )
+            })?;
+            state.size += line.len() as u64;
+        }
+
+        state.file.flush().map_err(|err| {
+            (
+                logs.len(),
+                format!("[dlog::sinks] Cannot write to {:?}: {}", self.options.path, err),
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> bool {
+        true
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        let (writer, batch) = (self.writer.clone(), logs.to_vec());
+        let res = tokio::task::spawn_blocking(move || writer.write(&batch))
+            .await
+            .map_err(|err| Log::new(Priority::Trace, format!("[dlog::sinks] File writer failed: {}", err)))?;
+
+        // Only the logs which were not written yet are retried, so no line is written twice
+        res.map_err(|(written, err)| {
+            SinkError::partial(Log::new(Priority::Trace, err), (written..logs.len()).collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dlog_file_sink_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Writes every text in its own batch to a file which rotates after each log
+    fn write(dir: &std::path::Path, max_archives: usize, compress: bool, texts: &[&str]) -> PathBuf {
+        let mut options = FileSinkOptions::new(dir.join("app.log"));
+        options.rotation = Rotation::Size(1);
+        options.max_archives = max_archives;
+        options.compress = compress;
+
+        let sink = FileSink::new(options).unwrap();
+        for text in texts {
+            sink.writer.write(&[Log::new(Priority::Info, *text)]).unwrap();
+        }
+        dir.join("app.log")
+    }
+
+    fn read(path: PathBuf) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rotates_and_prunes_archives() {
+        let dir = temp_dir("rotate");
+        let path = write(&dir, 2, false, &["first", "second", "third", "fourth"]);
+        assert!(read(path.clone()).contains("fourth"));
+        assert!(read(dir.join("app.log.1")).contains("third"));
+        assert!(read(dir.join("app.log.2")).contains("second"));
+        assert!(!dir.join("app.log.3").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn compresses_archives() {
+        use std::io::Read;
+
+        let dir = temp_dir("compress");
+        let path = write(&dir, 1, true, &["first", "second"]);
+        assert!(read(path).contains("second"));
+        assert!(!dir.join("app.log.1").exists());
+
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(File::open(dir.join("app.log.1.gz")).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert!(text.contains("first"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn deletes_the_file_without_archives() {
+        let dir = temp_dir("discard");
+        let path = write(&dir, 0, false, &["first", "second"]);
+        let text = read(path);
+        assert!(text.contains("second") && !text.contains("first"));
+        assert!(!dir.join("app.log.1").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn names_differ_per_directory() {
+        let dir = temp_dir("name");
+        let first = FileSink::new(FileSinkOptions::new(dir.join("a").join("app.log"))).unwrap();
+        let second = FileSink::new(FileSinkOptions::new(dir.join("b").join("app.log"))).unwrap();
+        assert_ne!(first.name(), second.name());
+        assert!(first.name().starts_with("file_app_"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+use crate::models::{Log, Priority};
+use crate::sinks::{async_trait, sanitize, Sink, SinkError};
+
+const FLUENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub enum FluentAddress {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+pub enum FluentMode {
+    Forward,
+    PackedForward,
+}
+
+pub struct FluentSinkOptions {
+    pub address: FluentAddress,
+    pub tag: String,
+    pub mode: FluentMode,
+    pub ack: bool,
+}
+
+impl FluentSinkOptions {
+    pub fn new(address: FluentAddress, tag: impl Into<String>) -> Self {
+        Self {
+            address,
+            tag: tag.into(),
+            mode: FluentMode::Forward,
+            ack: false,
+        }
+    }
+}
+
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+#[derive(Deserialize)]
+struct Ack {
+    ack: String,
+}
+
+/// Forwards logs to Fluentd or Fluent Bit using the Fluent Forward protocol. The connection is kept
+/// open between batches and reestablished on the next batch if it breaks.
+pub struct FluentSink {
+    name: String,
+    options: FluentSinkOptions,
+    connection: Mutex<Option<Box<dyn Connection>>>,
+    chunks: AtomicU64,
+}
+
+impl FluentSink {
+    pub fn new(options: FluentSinkOptions) -> Result<Self, String> {
+        if options.tag.is_empty() {
+            return Err(String::from("[dlog::sinks] The Fluent tag must not be empty"));
+        }
+
+        let name = match &options.address {
+            FluentAddress::Tcp(address) => format!("fluent_{}", sanitize(address)),
+            #[cfg(unix)]
+            FluentAddress::Unix(path) => format!("fluent_{}", sanitize(&path.to_string_lossy())),
+        };
+
+        Ok(Self {
+            name,
+            options,
+            connection: Mutex::new(None),
+            chunks: AtomicU64::new(0),
+        })
+    }
+
+    async fn connect(&self) -> Result<Box<dyn Connection>, String> {
+        let connection: Box<dyn Connection> = match &self.options.address {
+            FluentAddress::Tcp(address) => Box::new(
+                timeout(FLUENT_TIMEOUT, tokio::net::TcpStream::connect(address))
+                    .await
+                    .map_err(|_| format!("[dlog] Fluent connection to {} timed out", address))?
+                    .map_err(|err| format!("[dlog] Fluent connection error: {}", err))?,
+            ),
+            #[cfg(unix)]
+            FluentAddress::Unix(path) => Box::new(
+                tokio::net::UnixStream::connect(path)
+                    .await
+                    .map_err(|err| format!("[dlog] Fluent connection error: {}", err))?,
+            ),
+        };
+        Ok(connection)
+    }
+
+    fn chunk_id(&self) -> String {
+        let nanos = time::OffsetDateTime::now_utc().unix_timestamp_nanos();
+        format!("{:x}{:x}", nanos, self.chunks.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn encode(&self, logs: &[Log], chunk: &str) -> Result<Vec<u8>, String> {
+        let mut buf = Vec::new();
+        msgpack::encode_message(&mut buf, &self.options, logs, chunk)
+            .map_err(|err| format!("[dlog::sinks] Failed to encode Fluent message: {}", err))?;
+        Ok(buf)
+    }
+
+    async fn send(&self, connection: &mut Box<dyn Connection>, message: &[u8], chunk: &str) -> Result<(), String> {
+        timeout(FLUENT_TIMEOUT, connection.write_all(message))
+            .await
+            .map_err(|_| String::from("[dlog] Fluent write timed out"))?
+            .map_err(|err| format!("[dlog] Fluent connection error: {}", err))?;
+
+        if self.options.ack {
+            let mut response = [0u8; 256];
+            let len = timeout(FLUENT_TIMEOUT, connection.read(&mut response))
+                .await
+                .map_err(|_| String::from("[dlog] Fluent ack timed out"))?
+                .map_err(|err| format!("[dlog] Fluent connection error: {}", err))?;
+
+            match rmp_serde::from_slice::<Ack>(&response[..len]) {
+                Ok(ack) if ack.ack == chunk => (),
+                _ => return Err(String::from("[dlog] Fluent ack mismatch")),
+            }
+        }
+        Ok(())
+    }
+
+    async fn forward(&self, logs: &[Log]) -> Result<(), String> {
+        let chunk = self.chunk_id();
+        let message = self.encode(logs, &chunk)?;
+
+        let mut guard = self.connection.lock().await;
+        let mut connection = match guard.take() {
+            Some(connection) => connection,
+            None => self.connect().await?,
+        };
+
+        self.send(&mut connection, &message, &chunk).await?;
+        *guard = Some(connection);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for FluentSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> bool {
+        let mut guard = self.connection.lock().await;
+        if guard.is_none() {
+            *guard = self.connect().await.ok();
+        }
+        guard.is_some()
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        self.forward(logs).await.map_err(|err| Log::new(Priority::Trace, err).into())
+    }
+}
+
+mod msgpack {
+    use rmp::encode::{self, ValueWriteError};
+
+    use crate::models::Log;
+    use crate::sinks::fluent::{FluentMode, FluentSinkOptions};
+
+    const EVENT_TIME: i8 = 0;
+
+    pub fn encode_message(
+        buf: &mut Vec<u8>,
+        options: &FluentSinkOptions,
+        logs: &[Log],
+        chunk: &str,
+    ) -> Result<(), ValueWriteError> {
+        encode::write_array_len(buf, 3)?;
+        encode::write_str(buf, &options.tag)?;
+
+        match options.mode {
+            FluentMode::Forward => {
+                encode::write_array_len(buf, logs.len() as u32)?;
+                for log in logs {
+                    encode_entry(buf, log)?;
+                }
+            }
+            FluentMode::PackedForward => {
+                let mut entries = Vec::new();
+                for log in logs {
+                    encode_entry(&mut entries, log)?;
+                }
+                encode::write_bin(buf, &entries)?;
+            }
+        }
+
+        encode::write_map_len(buf, if options.ack { 2 } else { 1 })?;
+        encode::write_str(buf, "size")?;
+        encode::write_uint(buf, logs.len() as u64)?;
+        if options.ack {
+            encode::write_str(buf, "chunk")?;
+            encode::write_str(buf, chunk)?;
+        }
+        Ok(())
+    }
+
+    fn encode_entry(buf: &mut Vec<u8>, log: &Log) -> Result<(), ValueWriteError> {
+        encode::write_array_len(buf, 2)?;
+        encode::write_ext_meta(buf, 8, EVENT_TIME)?;
+        buf.extend_from_slice(&(log.timestamp.unix_timestamp() as u32).to_be_bytes());
+        buf.extend_from_slice(&log.timestamp.nanosecond().to_be_bytes());
+
+        encode::write_map_len(buf, 2)?;
+        encode::write_str(buf, "priority")?;
+        encode::write_str(buf, &log.priority.to_string())?;
+        encode::write_str(buf, "message")?;
+        encode::write_str(buf, &log.text)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use serde_json::json;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+use crate::models::{Log, Priority};
+use crate::sinks::{async_trait, sanitize, Sink, SinkError};
+
+const GELF_TIMEOUT: Duration = Duration::from_secs(5);
+const GELF_CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const GELF_CHUNK_HEADER: usize = 12;
+const GELF_MAX_CHUNKS: usize = 128;
+const DEFAULT_CHUNK_SIZE: usize = 1420;
+
+pub enum GelfTransport {
+    Udp,
+    Tcp,
+}
+
+pub enum GelfCompression {
+    None,
+    Gzip,
+    Zlib,
+}
+
+pub struct GelfSinkOptions {
+    pub address: String,
+    pub host: String,
+    pub transport: GelfTransport,
+    pub compression: GelfCompression,
+    pub chunk_size: usize,
+    pub fields: Vec<(String, String)>,
+}
+
+impl GelfSinkOptions {
+    pub fn new(address: impl Into<String>, transport: GelfTransport) -> Self {
+        Self {
+            address: address.into(),
+            host: std::env::var("HOSTNAME").unwrap_or_else(|_| String::from("dlog")),
+            transport,
+            compression: GelfCompression::Gzip,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            fields: Vec::new(),
+        }
+    }
+}
+
+/// Sends logs to Graylog using GELF, either as (chunked) UDP datagrams or as null-delimited
+/// messages over TCP. Compression only applies to UDP as GELF over TCP does not support it.
+pub struct GelfSink {
+    name: String,
+    options: GelfSinkOptions,
+    tcp: Mutex<Option<TcpStream>>,
+    messages: AtomicU64,
+}
+
+impl GelfSink {
+    pub fn new(options: GelfSinkOptions) -> Result<Self, String> {
+        if options.chunk_size <= GELF_CHUNK_HEADER {
+            return Err(format!(
+                "[dlog::sinks] The GELF chunk size must be larger than {} bytes",
+                GELF_CHUNK_HEADER
+            ));
+        }
+
+        for (key, _) in &options.fields {
+            if key == "_id" || !key.starts_with('_') || key.len() < 2 {
+                return Err(format!("[dlog::sinks] Invalid GELF additional field: {}", key));
+            }
+        }
+
+        Ok(Self {
+            name: format!("gelf_{}", sanitize(&options.address)),
+            options,
+            tcp: Mutex::new(None),
+            messages: AtomicU64::new(0),
+        })
+    }
+
+    fn encode(&self, log: &Log) -> Vec<u8> {
+        let short = log.text.lines().next().unwrap_or_default();
+        let mut message = json!({
+            "version": "1.1",
+            "host": self.options.host,
+            "short_message": if short.is_empty() { "-" } else { short },
+            "timestamp": log.timestamp.unix_timestamp_nanos() as f64 / 1_000_000_000f64,
+            "level": level(log.priority),
+            "_priority": log.priority.to_string(),
+        });
+
+        if short.len() != log.text.len() {
+            message["full_message"] = json!(log.text);
+        }
+
+        for (key, value) in &self.options.fields {
+            message[key] = json!(value);
+        }
+
+        serde_json::to_vec(&message).unwrap_or_default()
+    }
+
+    fn compress(&self, message: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self.options.compression {
+            GelfCompression::None => Ok(message),
+            GelfCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&message)?;
+                encoder.finish()
+            }
+            GelfCompression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&message)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    fn message_id(&self) -> [u8; 8] {
+        let nanos = time::OffsetDateTime::now_utc().unix_timestamp_nanos() as u64;
+        (nanos ^ self.messages.fetch_add(1, Ordering::Relaxed).rotate_left(48)).to_be_bytes()
+    }
+
+    /// Returns the indices of the messages which were dropped for exceeding the chunk limit. If a
+    /// send fails, only the messages from that one on are marked as failed, so no message is sent
+    /// twice.
+    async fn send_udp(&self, logs: &[Log]) -> Result<Vec<usize>, SinkError> {
+        let address = tokio::net::lookup_host(&self.options.address)
+            .await
+            .ok()
+            .and_then(|mut val| val.next())
+            .ok_or_else(|| {
+                Log::new(
+                    Priority::Trace,
+                    format!("[dlog] Cannot resolve GELF address {}", self.options.address),
+                )
+            })?;
+
+        let socket = UdpSocket::bind(if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })
+            .await
+            .map_err(|err| Log::new(Priority::Trace, format!("[dlog] GELF socket error: {}", err)))?;
+        socket
+            .connect(address)
+            .await
+            .map_err(|err| Log::new(Priority::Trace, format!("[dlog] GELF connection error: {}", err)))?;
+
+        let mut oversized = Vec::new();
+        for (position, log) in logs.iter().enumerate() {
+            match self.send_datagrams(&socket, log).await {
+                Ok(true) => (),
+                Ok(false) => oversized.push(position),
+                Err(err) => {
+                    return Err(
+                        SinkError::partial(Log::new(Priority::Trace, err), (position..logs.len()).collect())
+                            .with_rejected(oversized),
+                    )
+                }
+            }
+        }
+        Ok(oversized)
+    }
+
+    /// Returns false if the message exceeds the chunk limit and was not sent
+    async fn send_datagrams(&self, socket: &UdpSocket, log: &Log) -> Result<bool, String> {
+        let message = self
+            .compress(self.encode(log))
+            .map_err(|err| format!("[dlog::sinks] Failed to compress GELF message: {}", err))?;
+
+        if message.len() <= self.options.chunk_size {
+            socket
+                .send(&message)
+                .await
+                .map_err(|err| format!("[dlog] GELF connection error: {}", err))?;
+            return Ok(true);
+        }
+
+        let chunks = message
+            .chunks(self.options.chunk_size - GELF_CHUNK_HEADER)
+            .collect::<Vec<&[u8]>>();
+        if chunks.len() > GELF_MAX_CHUNKS {
+            return Ok(false);
+        }
+
+        let id = self.message_id();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let mut datagram = Vec::with_capacity(GELF_CHUNK_HEADER + chunk.len());
+            datagram.extend_from_slice(&GELF_CHUNK_MAGIC);
+            datagram.extend_from_slice(&id);
+            datagram.push(index as u8);
+            datagram.push(chunks.len() as u8);
+            datagram.extend_from_slice(chunk);
+            socket
+                .send(&datagram)
+                .await
+                .map_err(|err| format!("[dlog] GELF connection error: {}", err))?;
+        }
+        Ok(true)
+    }
+
+    async fn send_tcp(&self, logs: &[Log]) -> Result<(), String> {
+        let mut frames = Vec::new();
+        for log in logs {
+            frames.extend(self.encode(log));
+            frames.push(0);
+        }
+
+        let mut guard = self.tcp.lock().await;
+        let mut stream = match guard.take() {
+            Some(stream) => stream,
+            None => self.connect().await?,
+        };
+
+        timeout(GELF_TIMEOUT, stream.write_all(&frames))
+            .await
+            .map_err(|_| String::from("[dlog] GELF write timed out"))?
+            .map_err(|err| format!("[dlog] GELF connection error: {}", err))?;
+
+        *guard = Some(stream);
+        Ok(())
+    }
+
+    async fn connect(&self) -> Result<TcpStream, String> {
+        timeout(GELF_TIMEOUT, TcpStream::connect(&self.options.address))
+            .await
+            .map_err(|_| format!("[dlog] GELF connection to {} timed out", self.options.address))?
+            .map_err(|err| format!("[dlog] GELF connection error: {}", err))
+    }
+}
+
+#[async_trait]
+impl Sink for GelfSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> bool {
+        match self.options.transport {
+            GelfTransport::Udp => true,
+            GelfTransport::Tcp => {
+                let mut guard = self.tcp.lock().await;
+                if guard.is_none() {
+                    *guard = self.connect().await.ok();
+                }
+                guard.is_some()
+            }
+        }
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        let oversized = match self.options.transport {
+            GelfTransport::Udp => self.send_udp(logs).await?,
+            GelfTransport::Tcp => {
+                self.send_tcp(logs)
+                    .await
+                    .map_err(|err| Log::new(Priority::Trace, err))?;
+                Vec::new()
+            }
+        };
+
+        match oversized.is_empty() {
+            true => Ok(()),
+            false => {
+                let message = format!(
+                    "[dlog::sinks] Dropped {} GELF messages exceeding {} chunks",
+                    oversized.len(),
+                    GELF_MAX_CHUNKS
+                );
+                Err(SinkError::partial(Log::new(Priority::Trace, message), Vec::new()).with_rejected(oversized))
+            }
+        }
+    }
+}
+
+fn level(priority: Priority) -> u8 {
+    match priority {
+        Priority::Critical => 2,
+        Priority::Error => 3,
+        Priority::Warning => 4,
+        Priority::Info => 6,
+        Priority::Debug | Priority::Trace => 7,
+    }
+}
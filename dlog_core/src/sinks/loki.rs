@@ -0,0 +1,211 @@
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::models::{Log, Priority};
+use crate::sinks::{async_trait, client, priority_label, sanitize, Sink, SinkError};
+
+pub enum LokiEncoding {
+    Json,
+    Protobuf,
+}
+
+pub struct LokiSinkOptions {
+    pub url: String,
+    pub labels: Vec<(String, String)>,
+    pub tenant: Option<String>,
+    pub encoding: LokiEncoding,
+}
+
+impl LokiSinkOptions {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            labels: Vec::new(),
+            tenant: None,
+            encoding: LokiEncoding::Json,
+        }
+    }
+}
+
+type Labels = Vec<(String, String)>;
+
+/// Pushes logs into Grafana Loki via `/loki/api/v1/push`. Every priority is written into its own
+/// stream, labeled with `priority` and the configured static labels.
+pub struct LokiSink {
+    name: String,
+    client: reqwest::Client,
+    base: reqwest::Url,
+    options: LokiSinkOptions,
+}
+
+impl LokiSink {
+    pub fn new(options: LokiSinkOptions) -> Result<Self, String> {
+        let base = reqwest::Url::parse(&options.url)
+            .map_err(|err| format!("[dlog::sinks] Invalid Loki url {}: {}", options.url, err))?;
+
+        for (key, _) in &options.labels {
+            if !is_label_name(key) || key == "priority" {
+                return Err(format!("[dlog::sinks] Invalid Loki label name: {}", key));
+            }
+        }
+
+        Ok(Self {
+            name: format!("loki_{}", sanitize(base.host_str().unwrap_or_default())),
+            client: client()?,
+            base,
+            options,
+        })
+    }
+
+    fn streams<'a>(&self, logs: &'a [Log]) -> BTreeMap<&'static str, (Labels, Vec<&'a Log>)> {
+        let mut streams = BTreeMap::<&'static str, (Labels, Vec<&Log>)>::new();
+        for log in logs {
+            let priority = priority_label(log.priority);
+            streams
+                .entry(priority)
+                .or_insert_with(|| {
+                    let mut labels = self.options.labels.clone();
+                    labels.push((String::from("priority"), priority.to_owned()));
+                    (labels, Vec::new())
+                })
+                .1
+                .push(log);
+        }
+        streams
+    }
+
+    fn encode_json(&self, logs: &[Log]) -> Vec<u8> {
+        let streams = self
+            .streams(logs)
+            .into_values()
+            .map(|(labels, logs)| {
+                json!({
+                    "stream": labels.into_iter().collect::<BTreeMap<String, String>>(),
+                    "values": logs
+                        .iter()
+                        .map(|log| [log.timestamp.unix_timestamp_nanos().to_string(), log.text.clone()])
+                        .collect::<Vec<[String; 2]>>(),
+                })
+            })
+            .collect::<Vec<serde_json::Value>>();
+
+        serde_json::to_vec(&json!({ "streams": streams })).unwrap_or_default()
+    }
+
+    fn encode_protobuf(&self, logs: &[Log]) -> Result<Vec<u8>, String> {
+        let mut request = Vec::new();
+        for (labels, logs) in self.streams(logs).into_values() {
+            let mut stream = Vec::new();
+            protobuf::string(&mut stream, 1, &format_labels(&labels));
+            for log in logs {
+                let mut timestamp = Vec::new();
+                protobuf::varint_field(&mut timestamp, 1, log.timestamp.unix_timestamp() as u64);
+                protobuf::varint_field(&mut timestamp, 2, log.timestamp.nanosecond() as u64);
+
+                let mut entry = Vec::new();
+                protobuf::bytes(&mut entry, 1, &timestamp);
+                protobuf::string(&mut entry, 2, &log.text);
+                protobuf::bytes(&mut stream, 2, &entry);
+            }
+            protobuf::bytes(&mut request, 1, &stream);
+        }
+
+        snap::raw::Encoder::new()
+            .compress_vec(&request)
+            .map_err(|err| format!("[dlog::sinks] Failed to compress Loki request: {}", err))
+    }
+
+    async fn push(&self, logs: &[Log]) -> Result<reqwest::Response, String> {
+        let (content_type, body) = match self.options.encoding {
+            LokiEncoding::Json => ("application/json", self.encode_json(logs)),
+            LokiEncoding::Protobuf => ("application/x-protobuf", self.encode_protobuf(logs)?),
+        };
+
+        let url = self
+            .base
+            .join("loki/api/v1/push")
+            .map_err(|err| format!("[dlog::sinks] Invalid Loki url: {}", err))?;
+
+        let mut request = self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, HeaderValue::from_static(content_type))
+            .body(body)
+            .timeout(std::time::Duration::from_secs(5));
+
+        if let Some(tenant) = &self.options.tenant {
+            request = request.header("X-Scope-OrgID", tenant);
+        }
+
+        request
+            .send()
+            .await
+            .map_err(|err| format!("[dlog] Loki connection error: {}", err))
+    }
+}
+
+#[async_trait]
+impl Sink for LokiSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> bool {
+        match self.base.join("ready") {
+            Err(_) => false,
+            Ok(url) => matches!(self.client.get(url).send().await, Ok(res) if res.status().is_success()),
+        }
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        match self.push(logs).await {
+            Err(err) => Err(Log::new(Priority::Trace, err).into()),
+            Ok(val) if !val.status().is_success() => Err(Log::new(
+                Priority::Trace,
+                format!("[dlog] Loki ingestion failed: {}", val.text().await.unwrap_or_default()),
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn format_labels(labels: &Labels) -> String {
+    let labels = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<String>>();
+    format!("{{{}}}", labels.join(", "))
+}
+
+mod protobuf {
+    pub fn varint(buf: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        buf.push(value as u8);
+    }
+
+    pub fn varint_field(buf: &mut Vec<u8>, field: u32, value: u64) {
+        varint(buf, (field as u64) << 3);
+        varint(buf, value);
+    }
+
+    pub fn bytes(buf: &mut Vec<u8>, field: u32, value: &[u8]) {
+        varint(buf, ((field as u64) << 3) | 2);
+        varint(buf, value.len() as u64);
+        buf.extend_from_slice(value);
+    }
+
+    pub fn string(buf: &mut Vec<u8>, field: u32, value: &str) {
+        bytes(buf, field, value.as_bytes())
+    }
+}
@@ -0,0 +1,116 @@
+mod elastic;
+mod file;
+mod fluent;
+mod gelf;
+mod loki;
+mod splunk;
+
+pub use async_trait::async_trait;
+
+pub use crate::sinks::elastic::{ElasticSink, ElasticSinkOptions};
+pub use crate::sinks::file::{FileFormat, FileSink, FileSinkOptions, Rotation};
+pub use crate::sinks::fluent::{FluentAddress, FluentMode, FluentSink, FluentSinkOptions};
+pub use crate::sinks::gelf::{GelfCompression, GelfSink, GelfSinkOptions, GelfTransport};
+pub use crate::sinks::loki::{LokiEncoding, LokiSink, LokiSinkOptions};
+pub use crate::sinks::splunk::{SplunkSink, SplunkSinkOptions};
+
+use crate::models::{Log, Priority};
+
+const KEEP_ALIVE: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[async_trait]
+pub trait Sink: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn check(&self) -> bool;
+
+    /// Whether the sink writes to the local machine. Local sinks are not subject to the bandwidth
+    /// limits.
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError>;
+}
+
+/// Describes a failed delivery. If only some of the logs failed, `failed` contains their indices
+/// and only those are retried from the backlog. Logs in `rejected` were refused permanently and are
+/// counted as dropped instead of sent.
+pub struct SinkError {
+    pub message: Log,
+    pub failed: Option<Vec<usize>>,
+    pub rejected: Vec<usize>,
+}
+
+impl SinkError {
+    pub fn partial(message: Log, failed: Vec<usize>) -> Self {
+        Self {
+            message,
+            failed: Some(failed),
+            rejected: Vec::new(),
+        }
+    }
+
+    /// Marks the logs at the given indices as refused permanently, so they are neither retried nor
+    /// counted as sent
+    pub fn with_rejected(mut self, rejected: Vec<usize>) -> Self {
+        self.rejected = rejected;
+        self
+    }
+
+    /// Returns the logs to retry and the number of rejected logs
+    pub(crate) fn split(self, logs: Vec<Log>) -> (Vec<Log>, usize, Log) {
+        let rejected = self.rejected.len();
+        match self.failed {
+            None => (logs, 0, self.message),
+            Some(failed) => (
+                logs.into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| failed.contains(index))
+                    .map(|(_, log)| log)
+                    .collect(),
+                rejected,
+                self.message,
+            ),
+        }
+    }
+}
+
+impl From<Log> for SinkError {
+    fn from(message: Log) -> Self {
+        Self {
+            message,
+            failed: None,
+            rejected: Vec::new(),
+        }
+    }
+}
+
+pub(crate) fn client() -> Result<reqwest::Client, String> {
+    reqwest::ClientBuilder::new()
+        .connection_verbose(false)
+        .tcp_keepalive(KEEP_ALIVE)
+        .use_rustls_tls()
+        .build()
+        .map_err(|err| format!("Failed to build reqwest client: {}", err))
+}
+
+pub(crate) fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+pub(crate) fn priority_label(priority: Priority) -> &'static str {
+    match priority {
+        Priority::Critical => "critical",
+        Priority::Error => "error",
+        Priority::Warning => "warning",
+        Priority::Info => "info",
+        Priority::Debug => "debug",
+        Priority::Trace => "trace",
+    }
+}
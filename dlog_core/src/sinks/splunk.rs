@@ -0,0 +1,121 @@
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use serde_json::json;
+use std::fmt::Write;
+
+use crate::models::{Log, Priority};
+use crate::sinks::{async_trait, client, priority_label, sanitize, Sink, SinkError};
+
+pub struct SplunkSinkOptions {
+    pub url: String,
+    pub token: String,
+    pub index: Option<String>,
+    pub source: Option<String>,
+    pub sourcetype: Option<String>,
+}
+
+impl SplunkSinkOptions {
+    pub fn new(url: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            token: token.into(),
+            index: None,
+            source: None,
+            sourcetype: None,
+        }
+    }
+}
+
+/// Sends logs to a Splunk HTTP Event Collector. All logs of a batch are sent as concatenated events
+/// in a single request to `/services/collector/event`.
+pub struct SplunkSink {
+    name: String,
+    client: reqwest::Client,
+    base: reqwest::Url,
+    authorization: HeaderValue,
+    options: SplunkSinkOptions,
+}
+
+impl SplunkSink {
+    pub fn new(options: SplunkSinkOptions) -> Result<Self, String> {
+        let base = reqwest::Url::parse(&options.url)
+            .map_err(|err| format!("[dlog::sinks] Invalid Splunk url {}: {}", options.url, err))?;
+
+        let authorization = HeaderValue::from_str(&format!("Splunk {}", options.token))
+            .map_err(|_| String::from("[dlog::sinks] The Splunk token contains invalid characters"))?;
+
+        Ok(Self {
+            name: format!("splunk_{}", sanitize(base.host_str().unwrap_or_default())),
+            client: client()?,
+            base,
+            authorization,
+            options,
+        })
+    }
+
+    fn encode(&self, logs: &[Log]) -> String {
+        let mut body = String::new();
+        for log in logs {
+            let mut event = json!({
+                "time": log.timestamp.unix_timestamp_nanos() as f64 / 1_000_000_000f64,
+                "event": {
+                    "message": log.text,
+                    "severity": priority_label(log.priority),
+                },
+            });
+
+            for (key, value) in [
+                ("index", &self.options.index),
+                ("source", &self.options.source),
+                ("sourcetype", &self.options.sourcetype),
+            ] {
+                if let Some(value) = value {
+                    event[key] = json!(value);
+                }
+            }
+            let _ = write!(body, "{}", event);
+        }
+        body
+    }
+
+    async fn send(&self, logs: &[Log]) -> Result<reqwest::Response, String> {
+        let url = self
+            .base
+            .join("services/collector/event")
+            .map_err(|err| format!("[dlog::sinks] Invalid Splunk url: {}", err))?;
+
+        self.client
+            .post(url)
+            .header(AUTHORIZATION, self.authorization.clone())
+            .body(self.encode(logs))
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .map_err(|err| format!("[dlog] Splunk connection error: {}", err))
+    }
+}
+
+#[async_trait]
+impl Sink for SplunkSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> bool {
+        match self.base.join("services/collector/health") {
+            Err(_) => false,
+            Ok(url) => matches!(self.client.get(url).send().await, Ok(res) if res.status().is_success()),
+        }
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        match self.send(logs).await {
+            Err(err) => Err(Log::new(Priority::Trace, err).into()),
+            Ok(val) if !val.status().is_success() => Err(Log::new(
+                Priority::Trace,
+                format!("[dlog] Splunk ingestion failed: {}", val.text().await.unwrap_or_default()),
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+/// A snapshot of the counters collected by dlog since the logger was created
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub received: u64,
+    pub transformed: u64,
+    pub filtered: u64,
+    pub sent: u64,
+    pub retried: u64,
+    pub dropped: u64,
+    pub backlog_entries: u64,
+    pub backlog_bytes: u64,
+    pub last_error: Option<String>,
+    pub last_successful_send: Option<OffsetDateTime>,
+}
+
+impl Stats {
+    /// Renders the counters in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        for (name, kind, help, value) in [
+            ("dlog_logs_received_total", "counter", "Logs passed to dlog", self.received),
+            ("dlog_logs_transformed_total", "counter", "Logs processed by the transforms", self.transformed),
+            ("dlog_logs_filtered_total", "counter", "Logs dropped by a filter", self.filtered),
+            ("dlog_logs_sent_total", "counter", "Logs accepted by a sink", self.sent),
+            ("dlog_logs_retried_total", "counter", "Logs replayed from the backlog", self.retried),
+            ("dlog_logs_dropped_total", "counter", "Logs which were lost", self.dropped),
+            ("dlog_backlog_entries", "gauge", "Logs waiting in the backlog", self.backlog_entries),
+            ("dlog_backlog_bytes", "gauge", "Size of the backlog files on disk", self.backlog_bytes),
+        ] {
+            let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, kind, name, value);
+        }
+
+        if let Some(val) = self.last_successful_send {
+            let _ = writeln!(
+                text,
+                "# HELP dlog_last_successful_send_seconds Time of the last successful send\n\
+                 # TYPE dlog_last_successful_send_seconds gauge\n\
+                 dlog_last_successful_send_seconds {}",
+                val.unix_timestamp()
+            );
+        }
+        text
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    received: AtomicU64,
+    transformed: AtomicU64,
+    filtered: AtomicU64,
+    sent: AtomicU64,
+    retried: AtomicU64,
+    dropped: AtomicU64,
+    backlog_entries: AtomicI64,
+    backlog_bytes: AtomicI64,
+    last_error: Mutex<Option<String>>,
+    last_successful_send: Mutex<Option<OffsetDateTime>>,
+}
+
+impl Counters {
+    pub fn received(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn transformed(&self) {
+        self.transformed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn filtered(&self) {
+        self.filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn sent(&self, count: usize) {
+        // Failed sends report zero delivered logs, which must not count as a success
+        if count == 0 {
+            return;
+        }
+        self.sent.fetch_add(count as u64, Ordering::Relaxed);
+        *lock(&self.last_successful_send) = Some(OffsetDateTime::now_utc());
+    }
+
+    pub fn retried(&self, count: usize) {
+        self.retried.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn dropped(&self, count: usize) {
+        self.dropped.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn backlog(&self, entries: i64, bytes: i64) {
+        self.backlog_entries.fetch_add(entries, Ordering::Relaxed);
+        self.backlog_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn error(&self, message: &str) {
+        *lock(&self.last_error) = Some(message.to_string());
+    }
+
+    pub fn snapshot(&self) -> Stats {
+        Stats {
+            received: self.received.load(Ordering::Relaxed),
+            transformed: self.transformed.load(Ordering::Relaxed),
+            filtered: self.filtered.load(Ordering::Relaxed),
+            sent: self.sent.load(Ordering::Relaxed),
+            retried: self.retried.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            backlog_entries: self.backlog_entries.load(Ordering::Relaxed).max(0) as u64,
+            backlog_bytes: self.backlog_bytes.load(Ordering::Relaxed).max(0) as u64,
+            last_error: lock(&self.last_error).clone(),
+            last_successful_send: *lock(&self.last_successful_send),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(val) => val,
+        Err(err) => err.into_inner(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_records_sends_which_delivered_logs() {
+        let counters = Counters::default();
+        counters.sent(0);
+        assert_eq!(counters.snapshot().last_successful_send, None);
+
+        counters.sent(2);
+        let stats = counters.snapshot();
+        assert_eq!(stats.sent, 2);
+        assert!(stats.last_successful_send.is_some());
+    }
+}
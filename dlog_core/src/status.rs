@@ -0,0 +1,56 @@
+use std::sync::{Mutex, MutexGuard};
+
+use crate::models::Status;
+
+type Callback = Box<dyn Fn(Status) + Send + Sync>;
+
+/// Tracks the status of the logger and notifies all subscribers when it changes. Once the logger is
+/// shutting down, the status does not change anymore.
+pub struct Monitor {
+    status: Mutex<Status>,
+    callbacks: Mutex<Vec<Callback>>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Self {
+            status: Mutex::new(Status::Connected),
+            callbacks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn get(&self) -> Status {
+        *lock(&self.status)
+    }
+
+    pub fn set(&self, status: Status) {
+        {
+            let mut current = lock(&self.status);
+            if *current == status || *current == Status::ShuttingDown {
+                return;
+            }
+            *current = status;
+        }
+
+        for callback in lock(&self.callbacks).iter() {
+            callback(status);
+        }
+    }
+
+    pub fn subscribe(&self, callback: impl Fn(Status) + Send + Sync + 'static) {
+        lock(&self.callbacks).push(Box::new(callback));
+    }
+}
+
+impl std::fmt::Debug for Monitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Monitor").field("status", &self.get()).finish()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(val) => val,
+        Err(err) => err.into_inner(),
+    }
+}
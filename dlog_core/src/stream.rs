@@ -0,0 +1,200 @@
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+
+use crate::config::Config;
+use crate::ingest::{read_pem, HttpIngestor};
+use crate::models::Log;
+use crate::sinks::{async_trait, Sink, SinkError};
+
+const STREAM_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+const PROXY_VARIABLES: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Serialize)]
+struct Frame<'a> {
+    id: u64,
+    logs: &'a [Log],
+}
+
+#[derive(Deserialize)]
+struct Ack {
+    ack: u64,
+}
+
+struct Connection {
+    socket: Option<Socket>,
+    last_attempt: Option<Instant>,
+}
+
+/// Pushes logs over a persistent WebSocket connection and waits for the server to acknowledge
+/// every frame. While the connection is down, logs are ingested via regular batch requests.
+pub struct StreamingIngestor {
+    ingest: Arc<HttpIngestor>,
+    tls: Arc<rustls::ClientConfig>,
+    connection: Mutex<Connection>,
+    sequence: AtomicU64,
+}
+
+impl StreamingIngestor {
+    /// Uses the same root and client certificates as the batch ingest. Proxies are not supported
+    /// for the WebSocket connection, so streaming is rejected if one is configured.
+    pub fn new(ingest: Arc<HttpIngestor>, config: &Config) -> Result<Self, String> {
+        let env_proxy = config.env_proxy
+            && PROXY_VARIABLES
+                .iter()
+                .any(|name| matches!(std::env::var_os(name), Some(val) if !val.is_empty()));
+        if config.proxy.is_some() || env_proxy {
+            return Err(String::from(
+                "[dlog::stream] Streaming cannot be used through a proxy, disable either streaming or the proxy",
+            ));
+        }
+
+        Ok(Self {
+            ingest,
+            tls: Arc::new(tls_config(config)?),
+            connection: Mutex::new(Connection {
+                socket: None,
+                last_attempt: None,
+            }),
+            sequence: AtomicU64::new(0),
+        })
+    }
+
+    async fn connect(&self) -> Result<Socket, String> {
+        let mut request = self.ingest.stream_url()?.into_client_request().map_err(|err| err.to_string())?;
+        request.headers_mut().insert("API_KEY", self.ingest.api_key());
+
+        let connector = Connector::Rustls(self.tls.clone());
+        let connect = tokio_tungstenite::connect_async_tls_with_config(request, None, false, Some(connector));
+        let (socket, _) = timeout(STREAM_TIMEOUT, connect)
+            .await
+            .map_err(|_| String::from("connection timed out"))?
+            .map_err(|err| err.to_string())?;
+        Ok(socket)
+    }
+
+    async fn stream(&self, logs: &[Log]) -> Result<bool, String> {
+        let mut connection = self.connection.lock().await;
+        if connection.socket.is_none() {
+            if matches!(connection.last_attempt, Some(val) if val.elapsed() < RECONNECT_INTERVAL) {
+                return Ok(false);
+            }
+
+            connection.last_attempt = Some(Instant::now());
+            connection.socket = Some(self.connect().await?);
+        }
+
+        let socket = connection.socket.as_mut().unwrap();
+        let res = Self::send(socket, self.sequence.fetch_add(1, Ordering::Relaxed), logs).await;
+        if res.is_err() {
+            connection.socket = None;
+        }
+        res.map(|_| true)
+    }
+
+    async fn send(socket: &mut Socket, id: u64, logs: &[Log]) -> Result<(), String> {
+        let frame = serde_json::to_string(&Frame { id, logs }).map_err(|err| err.to_string())?;
+        timeout(STREAM_TIMEOUT, socket.send(Message::Text(frame)))
+            .await
+            .map_err(|_| String::from("send timed out"))?
+            .map_err(|err| err.to_string())?;
+
+        let deadline = Instant::now() + STREAM_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match timeout(remaining, socket.next()).await {
+                Err(_) => return Err(String::from("acknowledgement timed out")),
+                Ok(None) | Ok(Some(Ok(Message::Close(_)))) => return Err(String::from("connection closed")),
+                Ok(Some(Err(err))) => return Err(err.to_string()),
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    if matches!(serde_json::from_str::<Ack>(&text), Ok(val) if val.ack == id) {
+                        return Ok(());
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn tls_config(config: &Config) -> Result<rustls::ClientConfig, String> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|val| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(val.subject, val.spki, val.name_constraints)
+    }));
+
+    for path in &config.root_certificates {
+        let certificates = rustls_pemfile::certs(&mut read_pem(path)?.as_slice())
+            .map_err(|err| format!("Invalid root certificate {:?}: {}", path, err))?;
+        for certificate in certificates {
+            roots
+                .add(&rustls::Certificate(certificate))
+                .map_err(|err| format!("Invalid root certificate {:?}: {}", path, err))?;
+        }
+    }
+
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots);
+
+    match &config.client_certificate {
+        None => Ok(builder.with_no_client_auth()),
+        Some((certificate, key)) => {
+            let invalid = |err: String| format!("Invalid client certificate {:?}: {}", certificate, err);
+            let chain = rustls_pemfile::certs(&mut read_pem(certificate)?.as_slice())
+                .map_err(|err| invalid(err.to_string()))?
+                .into_iter()
+                .map(rustls::Certificate)
+                .collect();
+
+            let key = rustls_pemfile::read_all(&mut read_pem(key)?.as_slice())
+                .map_err(|err| invalid(err.to_string()))?
+                .into_iter()
+                .find_map(|item| match item {
+                    rustls_pemfile::Item::PKCS8Key(val)
+                    | rustls_pemfile::Item::RSAKey(val)
+                    | rustls_pemfile::Item::ECKey(val) => Some(rustls::PrivateKey(val)),
+                    _ => None,
+                })
+                .ok_or_else(|| invalid(String::from("no private key found")))?;
+
+            builder
+                .with_client_auth_cert(chain, key)
+                .map_err(|err| invalid(err.to_string()))
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for StreamingIngestor {
+    fn name(&self) -> &str {
+        "cloud"
+    }
+
+    async fn check(&self) -> bool {
+        self.ingest.check().await
+    }
+
+    async fn log_async(&self, logs: &[Log]) -> Result<(), SinkError> {
+        match self.stream(logs).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => (),
+            Err(err) => self
+                .ingest
+                .reporter()
+                .report(format!("[dlog::stream] Falling back to batch ingest: {}", err)),
+        }
+        Ok(self.ingest.log_async(logs).await?)
+    }
+}
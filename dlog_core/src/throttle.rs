@@ -0,0 +1,58 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::Log;
+
+const LOG_OVERHEAD: usize = 64;
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket limiting the number of bytes sent per second. A batch larger than the bucket is let
+/// through as a whole, following requests then wait until the debt is paid off.
+pub struct Throttle {
+    rate: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second = bytes_per_second.max(1);
+        Self {
+            rate: bytes_per_second as f64,
+            bucket: Mutex::new(Bucket {
+                tokens: bytes_per_second as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn acquire(&self, logs: &[Log]) {
+        let bytes = logs.iter().map(|log| log.text.len() + LOG_OVERHEAD).sum::<usize>();
+        let wait = {
+            let mut bucket = match self.bucket.lock() {
+                Ok(val) => val,
+                Err(err) => err.into_inner(),
+            };
+
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last_refill).as_secs_f64() * self.rate;
+            bucket.tokens = (bucket.tokens + refill).min(self.rate);
+            bucket.last_refill = now;
+
+            let wait = match bucket.tokens < 0f64 {
+                true => Duration::from_secs_f64((-bucket.tokens / self.rate).min(MAX_WAIT.as_secs_f64())),
+                false => Duration::ZERO,
+            };
+            bucket.tokens -= bytes as f64;
+            wait
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
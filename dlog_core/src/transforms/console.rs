@@ -1,20 +1,225 @@
-use time::format_description::FormatItem;
+use std::io::{IsTerminal, Write};
+use time::format_description::{FormatItem, OwnedFormatItem};
 use time::macros::format_description;
+use time::UtcOffset;
 
-use crate::models::Log;
-use crate::transforms::Transform;
-
-pub struct ConsoleTransform;
+use crate::models::{Log, Priority};
+use crate::transforms::{Transform, Verdict};
 
 const FORMAT: &[FormatItem] = format_description!("[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory]");
 
+/// Where the console output is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsoleStream {
+    #[default]
+    Stdout,
+    Stderr,
+    /// Writes warnings and more severe logs to stderr and everything else to stdout
+    Split,
+}
+
+/// How each log is rendered on the console
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsoleFormat {
+    /// `[timestamp] [Priority]: text`
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+    /// `time=... level=... msg="..."`
+    Logfmt,
+}
+
+/// Whether the text output is coloured by priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsoleColors {
+    /// Colours the output only if the stream is a terminal
+    Auto,
+    Always,
+    #[default]
+    Never,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsoleOptions {
+    pub stream: ConsoleStream,
+    pub format: ConsoleFormat,
+    pub colors: ConsoleColors,
+    /// A `time` format description, e.g. `[hour]:[minute]:[second]`. Uses the default format if unset.
+    pub timestamp_format: Option<String>,
+    /// Prints the timestamps in the local timezone instead of UTC
+    pub local_time: bool,
+    /// Only prints logs with this or a more severe priority. The logs are still sent regardless.
+    pub min_priority: Priority,
+}
+
+impl Default for ConsoleOptions {
+    fn default() -> Self {
+        Self {
+            stream: ConsoleStream::default(),
+            format: ConsoleFormat::default(),
+            colors: ConsoleColors::default(),
+            timestamp_format: None,
+            local_time: false,
+            min_priority: Priority::Trace,
+        }
+    }
+}
+
+pub struct ConsoleTransform {
+    options: ConsoleOptions,
+    timestamp_format: Option<OwnedFormatItem>,
+    offset: UtcOffset,
+    stdout_colors: bool,
+    stderr_colors: bool,
+    warning: Option<String>,
+}
+
+pub(crate) fn format(log: &Log) -> String {
+    format!(
+        "[{}] [{}]: {}",
+        log.timestamp.format(&FORMAT).unwrap(),
+        log.priority,
+        log.text
+    )
+}
+
+impl ConsoleTransform {
+    pub fn new(options: ConsoleOptions) -> Result<Self, String> {
+        let timestamp_format = match &options.timestamp_format {
+            Some(val) => Some(
+                time::format_description::parse_owned::<2>(val)
+                    .map_err(|err| format!("[dlog::transforms] Invalid timestamp format {}: {}", val, err))?,
+            ),
+            None => None,
+        };
+
+        // The local offset can only be determined reliably before other threads are spawned, so it
+        // is resolved once. The fallback is reported once the reporter exists.
+        let (offset, warning) = match options.local_time {
+            true => match UtcOffset::current_local_offset() {
+                Ok(val) => (val, None),
+                Err(err) => (
+                    UtcOffset::UTC,
+                    Some(format!(
+                        "[dlog::transforms] Cannot determine the local timezone, printing UTC times instead: {}",
+                        err
+                    )),
+                ),
+            },
+            false => (UtcOffset::UTC, None),
+        };
+
+        let colors = |terminal: bool| match options.colors {
+            ConsoleColors::Auto => terminal,
+            ConsoleColors::Always => true,
+            ConsoleColors::Never => false,
+        };
+
+        Ok(Self {
+            stdout_colors: colors(std::io::stdout().is_terminal()),
+            stderr_colors: colors(std::io::stderr().is_terminal()),
+            timestamp_format,
+            offset,
+            options,
+            warning,
+        })
+    }
+
+    /// Returns why the console output differs from the configured options, if it does
+    pub(crate) fn warning(&self) -> Option<&str> {
+        self.warning.as_deref()
+    }
+
+    fn timestamp(&self, log: &Log) -> String {
+        let timestamp = log.timestamp.to_offset(self.offset);
+        let formatted = match &self.timestamp_format {
+            Some(val) => timestamp.format(val),
+            None => timestamp.format(&FORMAT),
+        };
+        formatted.unwrap_or_default()
+    }
+
+    fn render(&self, log: &Log, colors: bool) -> String {
+        match self.options.format {
+            ConsoleFormat::Text => match colors {
+                true => format!(
+                    "\x1b[{}m[{}] [{}]:\x1b[0m {}",
+                    color(log.priority),
+                    self.timestamp(log),
+                    log.priority,
+                    log.text
+                ),
+                false => format!("[{}] [{}]: {}", self.timestamp(log), log.priority, log.text),
+            },
+            ConsoleFormat::Json => serde_json::json!({
+                "timestamp": self.timestamp(log),
+                "priority": log.priority,
+                "text": log.text,
+            })
+            .to_string(),
+            ConsoleFormat::Logfmt => format!(
+                "time={} level={} msg={}",
+                logfmt_value(&self.timestamp(log)),
+                log.priority,
+                logfmt_value(&log.text)
+            ),
+        }
+    }
+}
+
+impl Default for ConsoleTransform {
+    fn default() -> Self {
+        Self {
+            options: ConsoleOptions::default(),
+            timestamp_format: None,
+            offset: UtcOffset::UTC,
+            stdout_colors: false,
+            stderr_colors: false,
+            warning: None,
+        }
+    }
+}
+
+fn color(priority: Priority) -> &'static str {
+    match priority {
+        Priority::Critical => "1;31",
+        Priority::Error => "31",
+        Priority::Warning => "33",
+        Priority::Info => "32",
+        Priority::Debug => "36",
+        Priority::Trace => "90",
+    }
+}
+
+fn logfmt_value(value: &str) -> String {
+    match value.is_empty() || value.contains(|c: char| c == ' ' || c == '=' || c == '"' || c.is_control()) {
+        true => format!("{:?}", value),
+        false => value.to_string(),
+    }
+}
+
 impl Transform for ConsoleTransform {
-    fn apply(&self, log: &mut Log) {
-        println!(
-            "[{}] [{}]: {}",
-            log.timestamp.format(&FORMAT).unwrap(),
-            log.priority,
-            log.text
-        );
+    fn name(&self) -> &str {
+        "console"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
+        if log.priority.severity() < self.options.min_priority.severity() {
+            return Verdict::Keep;
+        }
+
+        let stderr = match self.options.stream {
+            ConsoleStream::Stdout => false,
+            ConsoleStream::Stderr => true,
+            ConsoleStream::Split => log.priority.severity() >= Priority::Warning.severity(),
+        };
+
+        // Errors while writing to the console must never prevent the log from being sent
+        let _ = match stderr {
+            true => writeln!(std::io::stderr().lock(), "{}", self.render(log, self.stderr_colors)),
+            false => writeln!(std::io::stdout().lock(), "{}", self.render(log, self.stdout_colors)),
+        };
+        Verdict::Keep
     }
 }
@@ -3,7 +3,7 @@ use static_init::dynamic;
 use std::fmt::Write;
 
 use crate::models::Log;
-use crate::transforms::Transform;
+use crate::transforms::{Transform, Verdict};
 
 #[dynamic]
 static NUMERIC: Regex = Regex::new(r#"^\d+$"#).unwrap();
@@ -14,7 +14,11 @@ static CREDIT_CARD: Regex = Regex::new(r#"^(?:4[0-9]{12}(?:[0-9]{3})?|[25][1-7][
 pub struct CreditCardTransform;
 
 impl Transform for CreditCardTransform {
-    fn apply(&self, log: &mut Log) {
+    fn name(&self) -> &str {
+        "credit_card_sanitizer"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
         let mut counter = 0;
         let mut message = Vec::<String>::new();
         for part in log.text.split(&[' ', '-'][..]) {
@@ -39,6 +43,7 @@ impl Transform for CreditCardTransform {
             }
         }
         log.text = message.join(" ");
+        Verdict::Keep
     }
 }
 
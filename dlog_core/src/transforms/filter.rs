@@ -0,0 +1,173 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::{Log, Priority};
+use crate::transforms::{Transform, Verdict};
+
+const MAX_TRACKED_MESSAGES: usize = 10_000;
+const EVICTED_MESSAGES: usize = MAX_TRACKED_MESSAGES / 10;
+
+/// Drops all logs below the given priority
+pub struct MinPriorityFilter {
+    min: Priority,
+}
+
+impl MinPriorityFilter {
+    pub fn new(min: Priority) -> Self {
+        Self { min }
+    }
+}
+
+impl Transform for MinPriorityFilter {
+    fn name(&self) -> &str {
+        "min_priority_filter"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
+        match log.priority.severity() >= self.min.severity() {
+            true => Verdict::Keep,
+            false => Verdict::Drop,
+        }
+    }
+}
+
+/// Keeps only logs whose text matches `include` (if set) and does not match `exclude` (if set)
+pub struct RegexFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+}
+
+impl RegexFilter {
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self, String> {
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|err| format!("[dlog::transforms] Invalid filter pattern {}: {}", pattern, err))
+        };
+
+        Ok(Self {
+            include: include.map(compile).transpose()?,
+            exclude: exclude.map(compile).transpose()?,
+        })
+    }
+}
+
+impl Transform for RegexFilter {
+    fn name(&self) -> &str {
+        "regex_filter"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
+        let included = match &self.include {
+            Some(val) => val.is_match(&log.text),
+            None => true,
+        };
+        let excluded = matches!(&self.exclude, Some(val) if val.is_match(&log.text));
+        match included && !excluded {
+            true => Verdict::Keep,
+            false => Verdict::Drop,
+        }
+    }
+}
+
+struct Occurrences {
+    window_start: Instant,
+    count: u32,
+}
+
+/// Keeps at most `max` logs with identical text per `window`. The first log after a window with
+/// suppressed logs mentions how many were suppressed.
+pub struct NoiseFilter {
+    max: u32,
+    window: Duration,
+    messages: Mutex<HashMap<String, Occurrences>>,
+}
+
+impl NoiseFilter {
+    pub fn new(max: u32, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            messages: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Transform for NoiseFilter {
+    fn name(&self) -> &str {
+        "noise_filter"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
+        let mut messages = match self.messages.lock() {
+            Ok(val) => val,
+            Err(err) => err.into_inner(),
+        };
+
+        // Expired messages are swept first. If the map is still full, the oldest messages are evicted in
+        // bulk, so the following logs do not pay for another sweep.
+        if messages.len() >= MAX_TRACKED_MESSAGES && !messages.contains_key(&log.text) {
+            messages.retain(|_, val| val.window_start.elapsed() < self.window);
+            if messages.len() >= MAX_TRACKED_MESSAGES {
+                evict_oldest(&mut messages, EVICTED_MESSAGES);
+            }
+        }
+
+        let now = Instant::now();
+        let occurrences = messages.entry(log.text.clone()).or_insert(Occurrences {
+            window_start: now,
+            count: 0,
+        });
+
+        if now.duration_since(occurrences.window_start) >= self.window {
+            let suppressed = occurrences.count.saturating_sub(self.max);
+            *occurrences = Occurrences {
+                window_start: now,
+                count: 0,
+            };
+
+            if suppressed > 0 {
+                log.text = format!("{} [dlog: {} identical messages were suppressed]", log.text, suppressed);
+            }
+        }
+
+        occurrences.count += 1;
+        match occurrences.count <= self.max {
+            true => Verdict::Keep,
+            false => Verdict::Drop,
+        }
+    }
+}
+
+fn evict_oldest(messages: &mut HashMap<String, Occurrences>, count: usize) {
+    let mut oldest = messages
+        .iter()
+        .map(|(text, val)| (val.window_start, text.clone()))
+        .collect::<Vec<_>>();
+    oldest.sort_unstable_by_key(|(window_start, _)| *window_start);
+    for (_, text) in oldest.into_iter().take(count) {
+        messages.remove(&text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_tracked_messages() {
+        let filter = NoiseFilter::new(1, Duration::from_secs(60));
+        for index in 0..MAX_TRACKED_MESSAGES * 2 {
+            filter.apply(&mut Log::new(Priority::Info, index.to_string()));
+        }
+        assert!(filter.messages.lock().unwrap().len() <= MAX_TRACKED_MESSAGES);
+    }
+
+    #[test]
+    fn drops_identical_messages() {
+        let filter = NoiseFilter::new(1, Duration::from_secs(60));
+        assert_eq!(filter.apply(&mut Log::new(Priority::Info, "a")), Verdict::Keep);
+        assert_eq!(filter.apply(&mut Log::new(Priority::Info, "a")), Verdict::Drop);
+        assert_eq!(filter.apply(&mut Log::new(Priority::Info, "b")), Verdict::Keep);
+    }
+}
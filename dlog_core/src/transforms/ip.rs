@@ -0,0 +1,234 @@
+use regex::Regex;
+use static_init::dynamic;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::Range;
+
+use crate::models::Log;
+use crate::transforms::{keyed_hash, Transform, Verdict};
+
+#[dynamic]
+static RUN: Regex = Regex::new(r#"[0-9A-Fa-f:.]+"#).unwrap();
+
+#[dynamic]
+static IPV4: Regex = Regex::new(r#"\d{1,3}(?:\.\d{1,3}){3}"#).unwrap();
+
+/// How IP addresses in the log text are anonymized
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpAnonymization {
+    /// Keeps only the network prefix, e.g. `192.168.1.0` for `192.168.1.42` with an IPv4 prefix of 24
+    Truncate { ipv4_prefix: u8, ipv6_prefix: u8 },
+    /// Replaces the address with a truncated HMAC-SHA256, so equal addresses can still be correlated
+    Hash { key: Vec<u8> },
+}
+
+impl Default for IpAnonymization {
+    fn default() -> Self {
+        Self::Truncate {
+            ipv4_prefix: 24,
+            ipv6_prefix: 48,
+        }
+    }
+}
+
+/// Anonymizes all IPv4 and IPv6 addresses found in the log text
+pub struct IpTransform {
+    anonymization: IpAnonymization,
+}
+
+impl IpTransform {
+    pub fn new(anonymization: IpAnonymization) -> Result<Self, String> {
+        match &anonymization {
+            IpAnonymization::Truncate {
+                ipv4_prefix,
+                ipv6_prefix,
+            } if *ipv4_prefix > 32 || *ipv6_prefix > 128 => {
+                return Err(format!(
+                    "[dlog::transforms] Invalid IP prefixes /{} and /{}",
+                    ipv4_prefix, ipv6_prefix
+                ))
+            }
+            IpAnonymization::Hash { key } if key.is_empty() => {
+                return Err(String::from("[dlog::transforms] The IP hash key must not be empty"))
+            }
+            _ => (),
+        }
+        Ok(Self { anonymization })
+    }
+
+    fn anonymize(&self, ip: IpAddr) -> String {
+        match &self.anonymization {
+            IpAnonymization::Truncate {
+                ipv4_prefix,
+                ipv6_prefix,
+            } => match ip {
+                IpAddr::V4(val) => truncate_ipv4(val, *ipv4_prefix).to_string(),
+                // IPv4 addresses embedded in IPv6 are truncated like plain IPv4 addresses
+                IpAddr::V6(val) => match val.to_ipv4_mapped() {
+                    Some(mapped) => truncate_ipv4(mapped, *ipv4_prefix).to_ipv6_mapped().to_string(),
+                    None => {
+                        let mask = u128::MAX.checked_shl(128 - *ipv6_prefix as u32).unwrap_or(0);
+                        Ipv6Addr::from(u128::from(val) & mask).to_string()
+                    }
+                },
+            },
+            IpAnonymization::Hash { key } => keyed_hash(key, &ip.to_string()),
+        }
+    }
+}
+
+fn truncate_ipv4(ip: Ipv4Addr, prefix: u8) -> Ipv4Addr {
+    let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+    Ipv4Addr::from(u32::from(ip) & mask)
+}
+
+impl Transform for IpTransform {
+    fn name(&self) -> &str {
+        "ip_anonymizer"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
+        let addresses = find_addresses(&log.text);
+        if addresses.is_empty() {
+            return Verdict::Keep;
+        }
+
+        let mut anonymized = String::with_capacity(log.text.len());
+        let mut last = 0;
+        for (range, ip) in addresses {
+            anonymized.push_str(&log.text[last..range.start]);
+            anonymized.push_str(&self.anonymize(ip));
+            last = range.end;
+        }
+        anonymized.push_str(&log.text[last..]);
+        log.text = anonymized;
+        Verdict::Keep
+    }
+}
+
+/// Finds all addresses in maximal runs of hex digits, colons and dots. A run is only read as an IPv6
+/// address if it is not part of a word, otherwise it is rescanned for IPv4 addresses, e.g. in
+/// `aa:bb:10.0.0.1`. A run glued to a word by a colon, like in `client:2001:db8::1`, is read from
+/// after that colon.
+fn find_addresses(text: &str) -> Vec<(Range<usize>, IpAddr)> {
+    let mut addresses = Vec::new();
+    for run in RUN.find_iter(text) {
+        match ipv6(text, run.range()) {
+            Some(val) => addresses.push(val),
+            None => addresses.extend(ipv4(text, run.range())),
+        }
+    }
+    addresses
+}
+
+fn ipv6(text: &str, range: Range<usize>) -> Option<(Range<usize>, IpAddr)> {
+    if is_word(text[range.end..].chars().next()) {
+        return None;
+    }
+
+    // The longest suffix which parses wins, e.g. `::1` in `ip:::1`
+    let run = &text[range.clone()];
+    let starts = std::iter::once(0)
+        .filter(|_| !is_word(text[..range.start].chars().next_back()))
+        .chain(run.match_indices(':').map(|(index, _)| index + 1));
+    for start in starts {
+        let suffix = &run[start..];
+        // Trailing punctuation like `::1:` or `::1.` ends a sentence rather than the address
+        for candidate in [suffix, suffix.trim_end_matches(&['.', ':'][..])] {
+            // Requiring a digit skips identifiers like `a::b`
+            if !candidate.contains(':') || !candidate.contains(|c: char| c.is_ascii_digit()) {
+                continue;
+            }
+            if let Ok(val) = candidate.parse::<Ipv6Addr>() {
+                let start = range.start + start;
+                return Some((start..start + candidate.len(), IpAddr::V6(val)));
+            }
+        }
+    }
+    None
+}
+
+fn ipv4(text: &str, range: Range<usize>) -> Vec<(Range<usize>, IpAddr)> {
+    IPV4.find_iter(&text[range.clone()])
+        .map(|val| range.start + val.start()..range.start + val.end())
+        .filter(|val| !extends_number(text[..val.start].chars().rev()) && !extends_number(text[val.end..].chars()))
+        .filter_map(|val| {
+            text[val.clone()]
+                .parse::<Ipv4Addr>()
+                .ok()
+                .map(|ip| (val, IpAddr::V4(ip)))
+        })
+        .collect()
+}
+
+fn is_word(c: Option<char>) -> bool {
+    matches!(c, Some(c) if c.is_alphanumeric() || c == '_')
+}
+
+/// Returns whether the characters next to a match continue it, e.g. in a version like `1.2.3.4.5`
+fn extends_number(mut chars: impl Iterator<Item = char>) -> bool {
+    match chars.next() {
+        Some('.') => matches!(chars.next(), Some(c) if c.is_ascii_digit()),
+        c => is_word(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Priority;
+
+    fn apply(anonymization: IpAnonymization, text: &str) -> String {
+        let mut log = Log::new(Priority::Info, text);
+        IpTransform::new(anonymization).unwrap().apply(&mut log);
+        log.text
+    }
+
+    fn hash() -> IpAnonymization {
+        IpAnonymization::Hash { key: b"key".to_vec() }
+    }
+
+    #[test]
+    fn truncates_addresses() {
+        let text = "from 192.168.1.42 and 2001:db8:abcd:12::1, [::1]:8080 or ::ffff:10.1.2.3.";
+        assert_eq!(
+            apply(IpAnonymization::default(), text),
+            "from 192.168.1.0 and 2001:db8:abcd::, [::]:8080 or ::ffff:10.1.2.0."
+        );
+    }
+
+    #[test]
+    fn keeps_rust_paths_and_module_prefixes() {
+        for text in [
+            "[dlog::backlog] Cannot cache",
+            "[dlog::diagnostics] x",
+            "use std::cmp::min",
+            "call a::b and Foo::bar()",
+            "at 10:30:00 with v1.2.3.4 and 1.2.3.4.5",
+        ] {
+            assert_eq!(apply(IpAnonymization::default(), text), text);
+            assert_eq!(apply(hash(), text), text);
+        }
+    }
+
+    #[test]
+    fn finds_ipv4_after_colons() {
+        assert_eq!(apply(IpAnonymization::default(), "aa:bb:10.0.0.1"), "aa:bb:10.0.0.0");
+        assert_eq!(apply(IpAnonymization::default(), "host:10.0.0.1"), "host:10.0.0.0");
+        assert!(!apply(hash(), "aa:bb:10.0.0.1").contains("10.0.0.1"));
+    }
+
+    #[test]
+    fn finds_ipv6_after_keys() {
+        assert_eq!(
+            apply(IpAnonymization::default(), "client:2001:db8:abcd:12::1 connected"),
+            "client:2001:db8:abcd:: connected"
+        );
+        assert_eq!(apply(IpAnonymization::default(), "ip:::1"), "ip:::");
+        assert_eq!(
+            apply(IpAnonymization::default(), "source:2001:db8:abcd:12::1."),
+            "source:2001:db8:abcd::."
+        );
+        assert!(!apply(hash(), "client:2001:db8:abcd:12::1").contains("12::1"));
+        assert!(!apply(hash(), "ip:::1").ends_with("::1"));
+    }
+}
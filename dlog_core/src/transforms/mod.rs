@@ -1,27 +1,115 @@
-mod console;
+pub(crate) mod console;
 mod credit_card;
 mod email;
+mod filter;
+mod ip;
+mod redact;
+mod secret;
 
-use crate::models::Log;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+
+use crate::models::{Log, Priority};
 use crate::transforms::console::ConsoleTransform;
 use crate::transforms::credit_card::CreditCardTransform;
 use crate::transforms::email::EmailTransform;
+use crate::transforms::secret::SecretTransform;
+
+pub use crate::transforms::console::{ConsoleColors, ConsoleFormat, ConsoleOptions, ConsoleStream};
+pub use crate::transforms::filter::{MinPriorityFilter, NoiseFilter, RegexFilter};
+pub use crate::transforms::ip::{IpAnonymization, IpTransform};
+pub use crate::transforms::redact::{RedactRule, RedactStrategy, RegexRedactTransform};
+
+const HASH_LENGTH: usize = 16;
+
+/// Decides whether a log continues through the pipeline or is discarded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Keep,
+    Drop,
+}
 
 pub trait Transform: Send + Sync {
-    fn apply(&self, log: &mut Log);
+    /// Identifies the transform when inserting other transforms relative to it
+    fn name(&self) -> &str;
+
+    fn apply(&self, log: &mut Log) -> Verdict;
 }
 
+/// The pipeline every log passes through before it is sent. The transforms are applied in order
+/// and the console output is written last, unless it was disabled.
 pub struct Transforms {
     transforms: Vec<Box<dyn Transform>>,
+    console: Option<ConsoleTransform>,
 }
 
 impl Transforms {
     pub fn new() -> Self {
         Self {
-            transforms: vec![Box::new(ConsoleTransform)],
+            transforms: Vec::new(),
+            console: Some(ConsoleTransform::default()),
         }
     }
 
+    /// Enables or disables printing every log to the console. Enabled by default.
+    pub fn set_console(&mut self, enabled: bool) {
+        self.console = match enabled {
+            true => Some(ConsoleTransform::default()),
+            false => None,
+        };
+    }
+
+    /// Enables printing every log to the console with the given stream, format and colours
+    pub fn set_console_options(&mut self, options: ConsoleOptions) -> Result<(), String> {
+        self.console = Some(ConsoleTransform::new(options)?);
+        Ok(())
+    }
+
+    /// Returns the problems found while configuring the pipeline, which are reported once the
+    /// logger starts
+    pub(crate) fn warnings(&self) -> Vec<&str> {
+        self.console.iter().filter_map(|console| console.warning()).collect()
+    }
+
+    /// Appends the transform to the end of the pipeline
+    pub fn push(&mut self, transform: impl Transform + 'static) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    /// Inserts the transform in front of the first transform called `name`
+    pub fn insert_before(&mut self, name: &str, transform: impl Transform + 'static) -> Result<(), String> {
+        let index = self.position(name)?;
+        self.transforms.insert(index, Box::new(transform));
+        Ok(())
+    }
+
+    /// Inserts the transform behind the first transform called `name`
+    pub fn insert_after(&mut self, name: &str, transform: impl Transform + 'static) -> Result<(), String> {
+        let index = self.position(name)?;
+        self.transforms.insert(index + 1, Box::new(transform));
+        Ok(())
+    }
+
+    /// Removes all transforms called `name` and returns whether any were found
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.transforms.len();
+        self.transforms.retain(|transform| transform.name() != name);
+        self.transforms.len() != len
+    }
+
+    /// Returns the names of all transforms in the order they are applied
+    pub fn names(&self) -> Vec<&str> {
+        self.transforms.iter().map(|transform| transform.name()).collect()
+    }
+
+    fn position(&self, name: &str) -> Result<usize, String> {
+        self.transforms
+            .iter()
+            .position(|transform| transform.name() == name)
+            .ok_or_else(|| format!("[dlog::transforms] No transform named {}", name))
+    }
+
     pub fn add_credit_card_sanitizer(&mut self, add: bool) {
         if add {
             self.transforms.insert(0, Box::new(CreditCardTransform));
@@ -33,6 +121,42 @@ impl Transforms {
             self.transforms.insert(0, Box::new(EmailTransform));
         }
     }
+
+    /// Redacts the matches of the given rules. Does nothing if no rules are given.
+    pub fn add_regex_redaction(&mut self, rules: Vec<RedactRule>) -> Result<(), String> {
+        if !rules.is_empty() {
+            self.transforms.insert(0, Box::new(RegexRedactTransform::new(rules)?));
+        }
+        Ok(())
+    }
+
+    pub fn add_secret_sanitizer(&mut self, add: bool) {
+        if add {
+            self.transforms.insert(0, Box::new(SecretTransform));
+        }
+    }
+
+    /// Truncates all IP addresses to their network prefix or replaces them with a keyed hash
+    pub fn add_ip_anonymizer(&mut self, anonymization: IpAnonymization) -> Result<(), String> {
+        self.transforms.insert(0, Box::new(IpTransform::new(anonymization)?));
+        Ok(())
+    }
+
+    /// Drops all logs below the given priority
+    pub fn add_min_priority_filter(&mut self, min: Priority) {
+        self.transforms.insert(0, Box::new(MinPriorityFilter::new(min)));
+    }
+
+    /// Keeps only logs matching `include` and not matching `exclude`
+    pub fn add_regex_filter(&mut self, include: Option<&str>, exclude: Option<&str>) -> Result<(), String> {
+        self.transforms.insert(0, Box::new(RegexFilter::new(include, exclude)?));
+        Ok(())
+    }
+
+    /// Keeps at most `max` logs with identical text per `window`
+    pub fn add_noise_filter(&mut self, max: u32, window: Duration) {
+        self.transforms.insert(0, Box::new(NoiseFilter::new(max, window)));
+    }
 }
 
 impl Default for Transforms {
@@ -42,9 +166,34 @@ impl Default for Transforms {
 }
 
 impl Transform for Transforms {
-    fn apply(&self, log: &mut Log) {
+    fn name(&self) -> &str {
+        "transforms"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
         for transform in &self.transforms {
-            transform.apply(log)
+            if transform.apply(log) == Verdict::Drop {
+                return Verdict::Drop;
+            }
+        }
+
+        match &self.console {
+            Some(console) => console.apply(log),
+            None => Verdict::Keep,
         }
     }
 }
+
+/// Returns a truncated HMAC-SHA256 of the value, so equal values can be correlated without the hash
+/// being reversible by a dictionary lookup
+pub(crate) fn keyed_hash(key: &[u8], value: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(value.as_bytes());
+    let hex = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("#{}", &hex[..HASH_LENGTH])
+}
@@ -0,0 +1,165 @@
+use regex::{Captures, Regex, RegexSet};
+use std::str::FromStr;
+
+use crate::models::Log;
+use crate::transforms::{keyed_hash, Transform, Verdict};
+
+const MASK: &str = "•";
+
+/// How a match of a redaction rule is replaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedactStrategy {
+    /// Replaces every character with `•`
+    Mask,
+    /// Keeps the first N characters and masks the rest. At most half of the characters are kept.
+    KeepFirst(usize),
+    /// Keeps the last N characters and masks the rest. At most half of the characters are kept.
+    KeepLast(usize),
+    /// Replaces the match with a truncated HMAC-SHA256, so equal values can still be correlated
+    /// without being looked up in a dictionary
+    Hash { key: Vec<u8> },
+}
+
+impl RedactStrategy {
+    fn redact(&self, value: &str) -> String {
+        let len = value.chars().count();
+        match self {
+            Self::Mask => MASK.repeat(len),
+            Self::KeepFirst(n) => {
+                let kept = (*n).min(len / 2);
+                value.chars().take(kept).collect::<String>() + &MASK.repeat(len - kept)
+            }
+            Self::KeepLast(n) => {
+                let kept = (*n).min(len / 2);
+                MASK.repeat(len - kept) + &value.chars().skip(len - kept).collect::<String>()
+            }
+            Self::Hash { key } => keyed_hash(key, value),
+        }
+    }
+}
+
+/// Parses `mask`, `hash:KEY`, `first:N` and `last:N`
+impl FromStr for RedactStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let count = |count: &str| {
+            count
+                .parse::<usize>()
+                .map_err(|_| format!("[dlog::transforms] Invalid redaction strategy {}", value))
+        };
+
+        match value.split_once(':') {
+            None if value == "mask" => Ok(Self::Mask),
+            Some(("hash", key)) if !key.is_empty() => Ok(Self::Hash {
+                key: key.as_bytes().to_vec(),
+            }),
+            Some(("first", val)) => Ok(Self::KeepFirst(count(val)?)),
+            Some(("last", val)) => Ok(Self::KeepLast(count(val)?)),
+            _ => Err(format!("[dlog::transforms] Invalid redaction strategy {}", value)),
+        }
+    }
+}
+
+/// A named pattern whose matches are replaced according to the strategy
+#[derive(Debug, Clone)]
+pub struct RedactRule {
+    pub name: String,
+    pub pattern: String,
+    pub strategy: RedactStrategy,
+}
+
+impl RedactRule {
+    pub fn new(name: impl Into<String>, pattern: impl Into<String>, strategy: RedactStrategy) -> Self {
+        Self {
+            name: name.into(),
+            pattern: pattern.into(),
+            strategy,
+        }
+    }
+}
+
+/// Redacts the matches of user-defined rules. All patterns are checked in a single pass and only the
+/// rules which matched are applied.
+pub struct RegexRedactTransform {
+    set: RegexSet,
+    rules: Vec<(Regex, RedactStrategy)>,
+}
+
+impl RegexRedactTransform {
+    pub fn new(rules: Vec<RedactRule>) -> Result<Self, String> {
+        let compiled = rules
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (regex, rule.strategy.clone()))
+                    .map_err(|err| format!("[dlog::transforms] Invalid redaction rule {}: {}", rule.name, err))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let set = RegexSet::new(rules.iter().map(|rule| &rule.pattern))
+            .map_err(|err| format!("[dlog::transforms] Invalid redaction rules: {}", err))?;
+
+        Ok(Self { set, rules: compiled })
+    }
+}
+
+impl Transform for RegexRedactTransform {
+    fn name(&self) -> &str {
+        "regex_redactor"
+    }
+
+    fn apply(&self, log: &mut Log) -> Verdict {
+        for index in self.set.matches(&log.text).iter() {
+            let (regex, strategy) = &self.rules[index];
+            log.text = regex
+                .replace_all(&log.text, |captures: &Captures| strategy.redact(&captures[0]))
+                .into_owned();
+        }
+        Verdict::Keep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Priority;
+
+    #[test]
+    fn masks_at_least_half_of_the_value() {
+        assert_eq!(RedactStrategy::KeepFirst(4).redact("1234567890"), "1234••••••");
+        assert_eq!(RedactStrategy::KeepLast(4).redact("1234567890"), "••••••7890");
+        assert_eq!(RedactStrategy::KeepFirst(10).redact("1234"), "12••");
+        assert_eq!(RedactStrategy::KeepLast(10).redact("1234"), "••34");
+        assert_eq!(RedactStrategy::KeepLast(10).redact("1"), "•");
+    }
+
+    #[test]
+    fn hashes_with_the_key() {
+        let hash = |key: &str| RedactStrategy::Hash { key: key.into() }.redact("1234");
+        assert_eq!(hash("a"), hash("a"));
+        assert_ne!(hash("a"), hash("b"));
+        assert!(!hash("a").contains("1234"));
+    }
+
+    #[test]
+    fn parses_strategies() {
+        assert_eq!("mask".parse(), Ok(RedactStrategy::Mask));
+        assert_eq!("last:4".parse(), Ok(RedactStrategy::KeepLast(4)));
+        assert_eq!("hash:key".parse(), Ok(RedactStrategy::Hash { key: b"key".to_vec() }));
+        assert!("hash".parse::<RedactStrategy>().is_err());
+        assert!("first:x".parse::<RedactStrategy>().is_err());
+    }
+
+    #[test]
+    fn redacts_matching_rules() {
+        let transform = RegexRedactTransform::new(vec![
+            RedactRule::new("customer", r"CUST-\d+", RedactStrategy::KeepFirst(5)),
+            RedactRule::new("token", r"tok_\w+", RedactStrategy::Mask),
+        ])
+        .unwrap();
+        let mut log = Log::new(Priority::Info, "CUST-12345 paid with tok_abc");
+        transform.apply(&mut log);
+        assert_eq!(log.text, "CUST-••••• paid with •••••••");
+    }
+}
@@ -2,143 +2,467 @@ use flume::RecvError;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::time::timeout;
 
 use crate::backlog::{Backlog, BacklogSignal};
-use crate::ingest::HttpIngestor;
-use crate::models::Log;
-use crate::transforms::{Transform, Transforms};
+use crate::config::Config;
+use crate::diagnostics::Reporter;
+use crate::ingest::{ApiKey, HttpIngestor};
+use crate::models::{Confirmation, FlushPolicy, FlushReport, Log, Priority, Status};
+use crate::sinks::Sink;
+use crate::stats::Counters;
+use crate::status::Monitor;
+use crate::stream::StreamingIngestor;
+use crate::throttle::Throttle;
+use crate::transforms::{Transform, Transforms, Verdict};
 use std::cmp::min;
 
 const FLUSH_CHUNK_SIZE: usize = 1_000;
 const DEFAULT_QUEUE_LENGTH: usize = 100_000;
 const MIN_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 const MIN_LOOP_INTERVAL: Duration = Duration::from_millis(50);
+const CLOUD_BACKLOG_FILE: &str = "backlog.dat";
 
 pub enum Signal {
     Log(Log),
-    Flush,
-    Exit,
+    Flush(Instant, flume::Sender<FlushReport>),
+    Confirm(Log, flume::Sender<Result<Confirmation, String>>),
+    /// Drains the queue until the deadline, the rest is written to the backlogs
+    Exit(Instant),
+}
+
+struct Destination {
+    sink: Arc<dyn Sink>,
+    throttle: Option<Arc<Throttle>>,
+    counters: Arc<Counters>,
+    backlog_sender: flume::Sender<BacklogSignal>,
+    is_backlog_empty: Arc<AtomicBool>,
+    backlog_flush_receiver: flume::Receiver<()>,
 }
 
 pub struct Worker {
-    exit: bool,
+    exit: Option<Instant>,
+    flush_interval: Duration,
+    flush_policies: Vec<(Priority, FlushPolicy)>,
     queue: Vec<Log>,
-    ingest: Arc<HttpIngestor>,
+    pub ingest: Option<Arc<HttpIngestor>>,
+    pub counters: Arc<Counters>,
+    pub monitor: Arc<Monitor>,
+    pub reporter: Arc<Reporter>,
+    destinations: Vec<Destination>,
     transforms: Transforms,
     signal_receiver: flume::Receiver<Signal>,
     pub signal_sender: flume::Sender<Signal>,
     flush_sender: flume::Sender<()>,
     pub flush_receiver: flume::Receiver<()>,
-    backlog_sender: flume::Sender<BacklogSignal>,
-    is_backlog_empty: Arc<AtomicBool>,
-    backlog_flush_receiver: flume::Receiver<()>,
 }
 
 impl Worker {
-    pub fn new(api_key: String, transforms: Transforms) -> Result<(Self, Backlog), String> {
-        let ingest = Arc::new(HttpIngestor::new(api_key)?);
+    pub fn new(api_key: String, transforms: Transforms, config: Config) -> Result<(Self, Vec<Backlog>), String> {
+        let api_key = match (config.cloud, &config.api_key_file) {
+            (false, _) => None,
+            (true, Some(path)) => Some(Arc::new(ApiKey::from_file(path)?)),
+            (true, None) => Some(Arc::new(ApiKey::new(&api_key)?)),
+        };
 
         let (signal_sender, signal_receiver) = flume::unbounded();
         let (flush_sender, flush_receiver) = flume::unbounded();
-        let backlog = Backlog::new(ingest.clone(), signal_sender.clone());
+        let reporter = Arc::new(Reporter::new(config.diagnostics.clone(), signal_sender.clone()));
+        for warning in transforms.warnings() {
+            reporter.report(warning);
+        }
+
+        let ingest = match api_key {
+            Some(api_key) => Some(Arc::new(HttpIngestor::new(api_key, &config, reporter.clone())?)),
+            None => None,
+        };
+
+        let cloud: Option<Arc<dyn Sink>> = match &ingest {
+            Some(ingest) if config.streaming => Some(Arc::new(StreamingIngestor::new(ingest.clone(), &config)?)),
+            Some(ingest) => Some(ingest.clone()),
+            None => None,
+        };
+
+        let mut sinks = config.sinks;
+        if let Some(cloud) = cloud {
+            sinks.insert(0, cloud);
+        }
+
+        let counters = Arc::new(Counters::default());
+
+        let mut destinations = Vec::with_capacity(sinks.len());
+        let mut backlogs = Vec::with_capacity(sinks.len());
+        for sink in sinks {
+            let file_name = match ingest.is_some() && destinations.is_empty() {
+                true => String::from(CLOUD_BACKLOG_FILE),
+                false => format!("backlog_{}.dat", sink.name()),
+            };
+
+            // Every remote destination has its own budget, so a batch uploaded to several sinks does
+            // not eat up the budget of the others
+            let is_local = sink.is_local();
+            let throttle = |limit: Option<u64>| match is_local {
+                true => None,
+                false => limit.map(|val| Arc::new(Throttle::new(val))),
+            };
+
+            let backlog = Backlog::new(
+                sink.clone(),
+                file_name,
+                throttle(config.backlog_bandwidth_limit),
+                counters.clone(),
+                reporter.clone(),
+            );
+            destinations.push(Destination {
+                sink,
+                throttle: throttle(config.bandwidth_limit),
+                counters: counters.clone(),
+                backlog_sender: backlog.signal_sender.clone(),
+                is_backlog_empty: backlog.is_empty.clone(),
+                backlog_flush_receiver: backlog.flush_receiver.clone(),
+            });
+            backlogs.push(backlog);
+        }
 
         let instance = Self {
-            exit: false,
+            exit: None,
+            flush_interval: match config.streaming {
+                true => MIN_LOOP_INTERVAL,
+                false => MIN_FLUSH_INTERVAL,
+            },
+            flush_policies: config.flush_policies,
             queue: Vec::with_capacity(DEFAULT_QUEUE_LENGTH),
             ingest,
+            counters,
+            monitor: Arc::new(Monitor::new()),
+            reporter,
+            destinations,
             transforms,
             signal_receiver,
             signal_sender,
             flush_sender,
             flush_receiver,
-            backlog_sender: backlog.signal_sender.clone(),
-            is_backlog_empty: backlog.is_empty.clone(),
-            backlog_flush_receiver: backlog.flush_receiver.clone(),
         };
 
-        Ok((instance, backlog))
+        Ok((instance, backlogs))
     }
 
     pub async fn has_valid_api_key(&self) -> bool {
-        self.ingest.has_valid_api_key().await
+        match &self.ingest {
+            Some(ingest) => ingest.has_valid_api_key().await,
+            None => true,
+        }
     }
 
     pub async fn start(&mut self) {
-        if let Err(err) = self.backlog_flush_receiver.recv_async().await {
-            eprintln!("[dlog::worker] Failed to receive ready signal: {}", err);
+        for destination in &self.destinations {
+            if let Err(err) = destination.backlog_flush_receiver.recv_async().await {
+                self.reporter
+                    .report(format!("[dlog::worker] Failed to receive ready signal: {}", err));
+            }
         }
 
         if let Err(err) = self.flush_sender.send_async(()).await {
-            eprintln!("[dlog::logger] Failed to send ready signal: {}", err);
+            self.reporter
+                .report(format!("[dlog::logger] Failed to send ready signal: {}", err));
         }
 
         let mut last_check = Instant::now();
-        while !self.exit {
+        while self.exit.is_none() {
             while let Ok(val) = self.signal_receiver.try_recv() {
                 self.receive(Ok(val)).await;
             }
 
             tokio::time::sleep(MIN_LOOP_INTERVAL).await;
-            if last_check.elapsed() >= MIN_FLUSH_INTERVAL {
+            if last_check.elapsed() >= self.flush_interval {
                 last_check = Instant::now();
                 self.flush().await;
+                self.update_status();
             }
         }
 
-        self.flush().await;
-        if let Err(err) = self.backlog_sender.send_async(BacklogSignal::Exit).await {
-            eprintln!("[dlog::worker] Could not send exit signal to backlog: {}", err);
-        };
+        self.monitor.set(Status::ShuttingDown);
+        let deadline = self.exit.unwrap_or_else(Instant::now);
+        while !self.queue.is_empty() && Instant::now() < deadline {
+            self.flush().await;
+        }
+
+        // Logs which could not be sent in time are persisted instead of being lost
+        let logs = std::mem::take(&mut self.queue);
+        for destination in &self.destinations {
+            if !logs.is_empty() {
+                destination.backlog(logs.clone(), &self.reporter, false).await;
+            }
+
+            if let Err(err) = destination.backlog_sender.send_async(BacklogSignal::Exit).await {
+                self.reporter
+                    .report(format!("[dlog::worker] Could not send exit signal to backlog: {}", err));
+                continue;
+            };
+
+            // Wait until the backlog has been written to disk
+            if let Err(err) = destination.backlog_flush_receiver.recv_async().await {
+                self.reporter
+                    .report(format!("[dlog::worker] Failed to receive exit signal from backlog: {}", err));
+            }
+        }
         if let Err(err) = self.flush_sender.send_async(()).await {
-            eprintln!("[dlog::worker] Failed to respond to exit signal: {}", err);
+            self.reporter
+                .report(format!("[dlog::worker] Failed to respond to exit signal: {}", err));
         }
     }
 
+    fn update_status(&self) {
+        let status = match &self.ingest {
+            Some(ingest) if ingest.is_rejected() => Status::AuthFailed,
+            _ if self
+                .destinations
+                .iter()
+                .any(|destination| !destination.is_backlog_empty.load(Ordering::Relaxed)) =>
+            {
+                Status::Backlogging
+            }
+            _ => Status::Connected,
+        };
+        self.monitor.set(status);
+    }
+
     async fn receive(&mut self, res: Result<Signal, RecvError>) {
         match res {
             Ok(Signal::Log(log)) => self.add(log).await,
-            Ok(Signal::Flush) => {
-                self.flush().await;
-                if let Err(err) = self.backlog_sender.send_async(BacklogSignal::Flush).await {
-                    eprintln!("[dlog::worker] Failed to send flush signal to backlog: {}", err);
+            Ok(Signal::Confirm(log, reply)) => {
+                let res = self.confirm(log).await;
+                if let Err(err) = reply.send_async(res).await {
+                    self.reporter
+                        .report(format!("[dlog::worker] Failed to respond to confirm signal: {}", err));
                 }
+            }
+            Ok(Signal::Flush(deadline, reply)) => {
+                // The caller stops waiting once the deadline expired, so a late report is discarded
+                let report = self.flush_until(deadline).await;
+                let _ = reply.send_async(report).await;
+            }
+            Ok(Signal::Exit(deadline)) => self.exit = Some(deadline),
+            Err(_) => self.exit = Some(Instant::now()),
+        };
+    }
+
+    async fn flush_until(&mut self, deadline: Instant) -> FlushReport {
+        let mut report = FlushReport::default();
+        while !self.queue.is_empty() && Instant::now() < deadline {
+            report.delivered += self.flush().await;
+        }
+        report.pending = self.queue.len();
 
-                if let Err(err) = self.backlog_flush_receiver.recv_async().await {
-                    eprintln!("[dlog::worker] Failed to receive flush signal from backlog: {}", err);
+        for destination in &self.destinations {
+            let (reply, response) = flume::bounded(1);
+            if let Err(err) = destination.backlog_sender.send_async(BacklogSignal::Flush(reply)).await {
+                self.reporter
+                    .report(format!("[dlog::worker] Failed to send flush signal to backlog: {}", err));
+                continue;
+            }
+
+            match timeout(deadline.saturating_duration_since(Instant::now()), response.recv_async()).await {
+                Ok(Ok((delivered, backlogged))) => {
+                    report.delivered += delivered;
+                    report.backlogged += backlogged;
                 }
+                Ok(Err(err)) => self.reporter.report(format!(
+                    "[dlog::worker] Failed to receive flush signal from backlog: {}",
+                    err
+                )),
+                Err(_) => report.deadline_expired = true,
+            }
+        }
+
+        report.deadline_expired |= Instant::now() > deadline;
+        report
+    }
+
+    async fn confirm(&mut self, mut log: Log) -> Result<Confirmation, String> {
+        // Preserve the order of all logs which were added before
+        while !self.queue.is_empty() {
+            self.flush().await;
+        }
 
-                if let Err(err) = self.flush_sender.send_async(()).await {
-                    eprintln!("[dlog::worker] Failed to respond to flush signal: {}", err);
+        if self.transform(&mut log) == Verdict::Drop {
+            return Ok(Confirmation::Filtered);
+        }
+
+        // Every destination gets the log, even if an earlier one failed
+        let mut confirmation = Confirmation::Delivered;
+        let mut errors = Vec::new();
+        for destination in &self.destinations {
+            match destination.confirm(log.clone(), &self.reporter).await {
+                Ok(Confirmation::Backlogged) => confirmation = Confirmation::Backlogged,
+                Ok(_) => (),
+                Err(err) => {
+                    self.reporter.report(err.as_str());
+                    errors.push(err);
                 }
             }
-            _ => self.exit = true,
-        };
+        }
+
+        match errors.is_empty() {
+            true => Ok(confirmation),
+            false => Err(format!(
+                "[dlog::worker] The log could not be confirmed by {} of {} destinations: {}",
+                errors.len(),
+                self.destinations.len(),
+                errors.join(" | ")
+            )),
+        }
+    }
+
+    fn transform(&self, log: &mut Log) -> Verdict {
+        if let Some(correction) = self.ingest.as_ref().and_then(|ingest| ingest.timestamp_correction()) {
+            log.timestamp += correction;
+        }
+
+        let verdict = self.transforms.apply(log);
+        match verdict {
+            Verdict::Keep => self.counters.transformed(),
+            Verdict::Drop => self.counters.filtered(),
+        }
+        verdict
     }
 
     async fn add(&mut self, mut log: Log) {
-        self.transforms.apply(&mut log);
+        if self.transform(&mut log) == Verdict::Drop {
+            return;
+        }
+
+        let policy = self
+            .flush_policies
+            .iter()
+            .find(|(priority, _)| *priority == log.priority)
+            .map_or(FlushPolicy::Batch, |(_, policy)| *policy);
+
         self.queue.push(log);
-        if self.queue.len() >= FLUSH_CHUNK_SIZE {
-            self.flush().await;
+        match policy {
+            FlushPolicy::Batch if self.queue.len() >= FLUSH_CHUNK_SIZE => {
+                self.flush().await;
+            }
+            FlushPolicy::Batch => (),
+            FlushPolicy::Immediate | FlushPolicy::Durable => {
+                while !self.queue.is_empty() {
+                    self.send_chunk(policy == FlushPolicy::Durable).await;
+                }
+            }
         }
     }
 
-    async fn flush(&mut self) {
+    async fn flush(&mut self) -> usize {
+        self.send_chunk(false).await
+    }
+
+    /// Sends the next chunk of the queue. If `durable` is set, waits until failed logs are written
+    /// to the backlog on disk.
+    async fn send_chunk(&mut self, durable: bool) -> usize {
+        let mut delivered = 0;
         if !self.queue.is_empty() {
-            let logs = self.queue.drain(..min(self.queue.len(), FLUSH_CHUNK_SIZE)).collect::<Vec<Log>>();
-            if !self.is_backlog_empty.load(Ordering::Relaxed) {
-                if let Err(err) = self.backlog_sender.send_async(BacklogSignal::Entries(logs)).await {
-                    eprintln!("[dlog::worker] Failed to send backlog signal: {}", err);
+            let mut logs = self
+                .queue
+                .drain(..min(self.queue.len(), FLUSH_CHUNK_SIZE))
+                .collect::<Vec<Log>>();
+            for (index, destination) in self.destinations.iter().enumerate() {
+                let logs = match index + 1 == self.destinations.len() {
+                    true => std::mem::take(&mut logs),
+                    false => logs.clone(),
+                };
+                delivered += destination.send(logs, &self.reporter, durable).await;
+            }
+        }
+        delivered
+    }
+}
+
+impl Destination {
+    async fn confirm(&self, log: Log, reporter: &Reporter) -> Result<Confirmation, String> {
+        if !self.is_backlog_empty.load(Ordering::Relaxed) {
+            return self.persist(vec![log]).await;
+        }
+
+        let logs = vec![log];
+        match self.sink.log_async(&logs).await {
+            Ok(_) => {
+                self.counters.sent(1);
+                Ok(Confirmation::Delivered)
+            }
+            Err(err) => {
+                self.counters.error(&err.message.text);
+                let (logs, rejected, log) = err.split(logs);
+                if rejected > 0 {
+                    self.counters.dropped(rejected);
+                    return Err(log.text);
                 }
-            } else if let Err(log) = self.ingest.log_async(&logs).await {
-                if let Err(err) = self.backlog_sender.send_async(BacklogSignal::Entries(logs)).await {
-                    eprintln!("[dlog::worker] Failed to send backlog signal: {}", err);
+
+                reporter.report(log.text);
+                match logs.is_empty() {
+                    true => {
+                        self.counters.sent(1);
+                        Ok(Confirmation::Delivered)
+                    }
+                    false => self.persist(logs).await,
                 }
+            }
+        }
+    }
 
-                if let Err(err) = self.signal_sender.send_async(Signal::Log(log)).await {
-                    eprintln!("[dlog::worker] Failed to send signal: {}", err);
+    async fn backlog(&self, logs: Vec<Log>, reporter: &Reporter, durable: bool) {
+        match durable {
+            true => {
+                if let Err(err) = self.persist(logs).await {
+                    reporter.report(err);
+                }
+            }
+            false => {
+                if let Err(err) = self.backlog_sender.send_async(BacklogSignal::Entries(logs)).await {
+                    reporter.report(format!("[dlog::worker] Failed to send backlog signal: {}", err));
                 }
             }
         }
     }
+
+    async fn persist(&self, logs: Vec<Log>) -> Result<Confirmation, String> {
+        let (reply, response) = flume::bounded(1);
+        if let Err(err) = self.backlog_sender.send_async(BacklogSignal::Persist(logs, reply)).await {
+            return Err(format!("[dlog::worker] Failed to send persist signal: {}", err));
+        }
+
+        match response.recv_async().await {
+            Ok(true) => Ok(Confirmation::Backlogged),
+            Ok(false) => Err(format!("[dlog::worker] Failed to write log to the {} backlog", self.sink.name())),
+            Err(err) => Err(format!("[dlog::worker] Failed to receive persist signal: {}", err)),
+        }
+    }
+
+    async fn send(&self, logs: Vec<Log>, reporter: &Reporter, durable: bool) -> usize {
+        if !self.is_backlog_empty.load(Ordering::Relaxed) {
+            self.backlog(logs, reporter, durable).await;
+            return 0;
+        }
+
+        if let Some(throttle) = &self.throttle {
+            throttle.acquire(&logs).await;
+        }
+
+        let count = logs.len();
+        if let Err(err) = self.sink.log_async(&logs).await {
+            self.counters.error(&err.message.text);
+            let (logs, rejected, log) = err.split(logs);
+            let delivered = count - logs.len() - rejected;
+            self.counters.sent(delivered);
+            self.counters.dropped(rejected);
+            if !logs.is_empty() {
+                self.backlog(logs, reporter, durable).await;
+            }
+
+            reporter.report(log.text);
+            delivered
+        } else {
+            self.counters.sent(count);
+            count
+        }
+    }
 }
@@ -1,8 +1,26 @@
+export interface RedactRule {
+    name: string;
+    pattern: string;
+    /** 'mask' (default), 'hash:<KEY>', 'first:<N>' or 'last:<N>' */
+    strategy?: string | undefined;
+}
+
 export interface Options {
     sanitize_emails?: boolean | undefined;
     sanitize_credit_cards?: boolean | undefined;
+    sanitize_secrets?: boolean | undefined;
+    handle_signals?: boolean | undefined;
+    redact?: RedactRule[] | undefined;
 }
 
-export function configure (api_key: string): undefined;
+export function configure (api_key: string, options?: Options): undefined;
+
+export function set_api_key (api_key: string): undefined;
+
+export type Status = 'connected' | 'backlogging' | 'auth_failed' | 'shutting_down';
+
+export function status (): Status | undefined;
+
+export function on_status_change (callback: (status: Status) => void): undefined;
 
 export function with_dlog<T>(api_key: string, handler: T): T;
\ No newline at end of file
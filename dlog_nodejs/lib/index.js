@@ -30,8 +30,15 @@ module.exports.configure = function (api_key, options) {
     else if (typeof api_key !== 'string') throw "[dlog] Please provide a valid API_KEY"
     else instance = addon.configure(api_key, options || {})
 
-    process.on('exit', addon.cleanUp.bind(null, instance));
-    process.on('SIGINT', addon.cleanUp.bind(null, instance));
+    const cleanUp = addon.cleanUp.bind(null, instance);
+    process.on('exit', cleanUp);
+    if ((options || {}).handle_signals !== false) {
+        // Drain before exiting, then re-raise the signal so the default behaviour applies
+        ['SIGINT', 'SIGTERM'].forEach(signal => process.once(signal, () => {
+            cleanUp();
+            process.kill(process.pid, signal);
+        }));
+    }
 
     const [error, warn, info, log, debug] = [
         console.error,
@@ -48,6 +55,22 @@ module.exports.configure = function (api_key, options) {
     console.debug = apply(DEBUG);
 }
 
+module.exports.set_api_key = function (api_key) {
+    if (!instance) throw "[dlog] configure(<API_KEY>) must be called first"
+    else if (typeof api_key !== 'string') throw "[dlog] Please provide a valid API_KEY"
+    else addon.setApiKey(instance, api_key)
+}
+
+module.exports.status = function () {
+    return instance ? addon.status(instance) : undefined
+}
+
+module.exports.on_status_change = function (callback) {
+    if (!instance) throw "[dlog] configure(<API_KEY>) must be called first"
+    else if (typeof callback !== 'function') throw "[dlog] Please provide a callback function"
+    else addon.onStatusChange(instance, callback)
+}
+
 module.exports.with_dlog = function (API_KEY, handler, options) {
     this.configure(API_KEY, options)
     return async function(...args) {
@@ -1,9 +1,11 @@
 use neon::prelude::*;
+use std::sync::Arc;
 
 mod extractor;
 
 use crate::extractor::Extractor;
-use dlog_core::transforms::Transforms;
+use dlog_core::config::Config;
+use dlog_core::transforms::{RedactRule, RedactStrategy, Transforms};
 
 pub struct Logger(dlog_core::Logger);
 
@@ -24,12 +26,36 @@ fn configure(mut cx: FunctionContext) -> JsResult<JsBox<Logger>> {
     let sanitize_credit_cards = options.get::<JsBoolean, _, _>(&mut cx, "sanitize_credit_cards").map_or(false, |kv| {
         kv.value(&mut cx)
     });
+    let sanitize_secrets = options.get::<JsBoolean, _, _>(&mut cx, "sanitize_secrets").map_or(false, |kv| {
+        kv.value(&mut cx)
+    });
 
     let mut transforms = Transforms::new();
     transforms.add_email_sanitizer(sanitize_emails);
     transforms.add_credit_card_sanitizer(sanitize_credit_cards);
+    transforms.add_secret_sanitizer(sanitize_secrets);
+
+    let mut rules = Vec::new();
+    if let Some(redact) = options.get_opt::<JsArray, _, _>(&mut cx, "redact")? {
+        for rule in redact.to_vec(&mut cx)? {
+            let rule = rule.downcast_or_throw::<JsObject, _>(&mut cx)?;
+            let name = rule.get::<JsString, _, _>(&mut cx, "name")?.value(&mut cx);
+            let pattern = rule.get::<JsString, _, _>(&mut cx, "pattern")?.value(&mut cx);
+            let strategy = match rule.get_opt::<JsString, _, _>(&mut cx, "strategy")? {
+                Some(val) => val.value(&mut cx),
+                None => String::from("mask"),
+            };
+            match strategy.parse::<RedactStrategy>() {
+                Err(err) => return cx.throw_error(err),
+                Ok(val) => rules.push(RedactRule::new(name, pattern, val)),
+            }
+        }
+    }
+    if let Err(err) = transforms.add_regex_redaction(rules) {
+        return cx.throw_error(err);
+    }
 
-    match dlog_core::Logger::new(api_key, transforms) {
+    match dlog_core::Logger::new(api_key, transforms, Config::new()) {
         Err(err) => cx.throw_error(err),
         Ok(val) => Ok(cx.boxed(Logger(val))),
     }
@@ -44,6 +70,37 @@ fn log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     }
 }
 
+fn set_api_key(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let api_key = cx.argument::<JsString>(1)?.value(&mut cx);
+    match cx.context()?.0.set_api_key(&api_key) {
+        Err(err) => cx.throw_error(err),
+        Ok(_) => Ok(JsUndefined::new(&mut cx)),
+    }
+}
+
+fn status(mut cx: FunctionContext) -> JsResult<JsString> {
+    let status = cx.context()?.0.status().to_string();
+    Ok(cx.string(status))
+}
+
+fn on_status_change(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let callback = Arc::new(cx.argument::<JsFunction>(1)?.root(&mut cx));
+    let mut channel = cx.channel();
+    channel.unref(&mut cx);
+
+    cx.context()?.0.on_status_change(move |status| {
+        let callback = callback.clone();
+        channel.send(move |mut cx| {
+            let callback = callback.to_inner(&mut cx);
+            let this = cx.undefined();
+            let args = vec![cx.string(status.to_string()).upcast::<JsValue>()];
+            callback.call(&mut cx, this, args)?;
+            Ok(())
+        });
+    });
+    Ok(JsUndefined::new(&mut cx))
+}
+
 fn flush(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     match cx.context()?.0.flush() {
         Err(err) => cx.throw_error(err),
@@ -57,6 +114,9 @@ fn main(mut cx: ModuleContext) -> NeonResult<()> {
     cx.export_function("cleanUp", clean_up)?;
     cx.export_function("log", log)?;
     cx.export_function("flush", flush)?;
+    cx.export_function("setApiKey", set_api_key)?;
+    cx.export_function("status", status)?;
+    cx.export_function("onStatusChange", on_status_change)?;
 
     Ok(())
 }
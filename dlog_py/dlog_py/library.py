@@ -3,10 +3,11 @@ from .dlog_py import PythonLogger as _PythonLogger
 
 
 class DlogLogger(logging.StreamHandler):
-    def __init__(self, api_key, sanitize_emails=True, sanitize_credit_cards=True):
+    def __init__(self, api_key, sanitize_emails=True, sanitize_credit_cards=True, redact=None, sanitize_secrets=False):
         logging.StreamHandler.__init__(self)
         try:
-            self.instance = _PythonLogger(api_key, sanitize_emails, sanitize_credit_cards)
+            rules = [(rule['name'], rule['pattern'], rule.get('strategy', 'mask')) for rule in redact or []]
+            self.instance = _PythonLogger(api_key, sanitize_emails, sanitize_credit_cards, sanitize_secrets, rules)
         except ValueError as ex:
             print(ex)
 
@@ -14,20 +15,41 @@ class DlogLogger(logging.StreamHandler):
         if hasattr(self, 'instance'):
             self.instance.log(record.levelno, self.format(record))
 
+    def set_api_key(self, api_key):
+        if hasattr(self, 'instance'):
+            self.instance.set_api_key(api_key)
+
+    def status(self):
+        if hasattr(self, 'instance'):
+            return self.instance.status()
+        return None
+
+    def on_status_change(self, callback):
+        if hasattr(self, 'instance'):
+            self.instance.on_status_change(callback)
+
     def flush(self) -> None:
         if hasattr(self, 'instance'):
             self.instance.flush()
 
+    def flush_with_deadline(self, seconds):
+        if hasattr(self, 'instance'):
+            delivered, backlogged, pending, deadline_expired = self.instance.flush_with_deadline(seconds)
+            return {'delivered': delivered, 'backlogged': backlogged, 'pending': pending,
+                    'deadline_expired': deadline_expired}
+        return None
+
     def close(self):
         if hasattr(self, 'instance'):
             self.instance.clean_up()
 
 
-def with_dlog(api_key, level=None, sanitize_emails=False, sanitize_credit_cards=False):
+def with_dlog(api_key, level=None, sanitize_emails=False, sanitize_credit_cards=False, flush_deadline=3.0, redact=None,
+              sanitize_secrets=False):
     if level is None:
         level = logging.WARNING
 
-    dlog = DlogLogger(api_key, sanitize_emails, sanitize_credit_cards)
+    dlog = DlogLogger(api_key, sanitize_emails, sanitize_credit_cards, redact, sanitize_secrets)
 
     logger = logging.getLogger('log')
     logger.setLevel(level)
@@ -36,7 +58,14 @@ def with_dlog(api_key, level=None, sanitize_emails=False, sanitize_credit_cards=
     def wrapper(handler):
         def inner(*args):
             res = handler(logger, *args)
-            dlog.flush()
+            try:
+                report = dlog.flush_with_deadline(flush_deadline)
+            except ValueError as ex:
+                print(ex)
+                return res
+
+            if report is not None and (report['deadline_expired'] or report['backlogged'] > 0 or report['pending'] > 0):
+                print('[dlog] Not all logs were delivered before the deadline: {}'.format(report))
             return res
 
         return inner
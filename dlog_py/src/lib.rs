@@ -1,8 +1,10 @@
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use std::time::Duration;
 
+use dlog_core::config::Config;
 use dlog_core::models::Priority;
-use dlog_core::transforms::Transforms;
+use dlog_core::transforms::{RedactRule, RedactStrategy, Transforms};
 
 #[pyclass]
 struct PythonLogger {
@@ -12,12 +14,26 @@ struct PythonLogger {
 #[pymethods]
 impl PythonLogger {
     #[new]
-    fn __new__(api_key: String, email_sanitizer: bool, credit_card_sanitizer: bool) -> PyResult<Self> {
+    fn __new__(
+        api_key: String,
+        email_sanitizer: bool,
+        credit_card_sanitizer: bool,
+        secret_sanitizer: bool,
+        redact_rules: Vec<(String, String, String)>,
+    ) -> PyResult<Self> {
         let mut transforms = Transforms::new();
         transforms.add_credit_card_sanitizer(credit_card_sanitizer);
         transforms.add_email_sanitizer(email_sanitizer);
+        transforms.add_secret_sanitizer(secret_sanitizer);
 
-        match dlog_core::Logger::new(api_key, transforms) {
+        let mut rules = Vec::with_capacity(redact_rules.len());
+        for (name, pattern, strategy) in redact_rules {
+            let strategy = strategy.parse::<RedactStrategy>().map_err(PyValueError::new_err)?;
+            rules.push(RedactRule::new(name, pattern, strategy));
+        }
+        transforms.add_regex_redaction(rules).map_err(PyValueError::new_err)?;
+
+        match dlog_core::Logger::new(api_key, transforms, Config::new()) {
             Err(err) => Err(PyValueError::new_err(err)),
             Ok(val) => Ok(Self { core: val }),
         }
@@ -30,15 +46,51 @@ impl PythonLogger {
         }
     }
 
-    fn flush(&self) -> PyResult<()> {
-        match self.core.flush() {
+    fn set_api_key(&self, api_key: String) -> PyResult<()> {
+        match self.core.set_api_key(&api_key) {
+            Err(err) => Err(PyValueError::new_err(err)),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    fn status(&self) -> String {
+        self.core.status().to_string()
+    }
+
+    fn on_status_change(&self, callback: PyObject) {
+        self.core.on_status_change(move |status| {
+            Python::with_gil(|py| {
+                if let Err(err) = callback.call1(py, (status.to_string(),)) {
+                    err.print(py);
+                }
+            })
+        })
+    }
+
+    fn flush(&self, py: Python<'_>) -> PyResult<()> {
+        // Releases the GIL, so status callbacks on the background thread cannot deadlock the flush
+        match py.allow_threads(|| self.core.flush()) {
             Err(err) => Err(PyValueError::new_err(err)),
             Ok(_) => Ok(()),
         }
     }
 
-    fn clean_up(&self) {
-        self.core.clean_up()
+    fn flush_with_deadline(&self, py: Python<'_>, seconds: f64) -> PyResult<(usize, usize, usize, bool)> {
+        let deadline = Duration::from_secs_f64(seconds.max(0f64));
+        match py.allow_threads(|| self.core.flush_with_deadline(deadline)) {
+            Err(err) => Err(PyValueError::new_err(err)),
+            Ok(val) => Ok((val.delivered, val.backlogged, val.pending, val.deadline_expired)),
+        }
+    }
+
+    fn clean_up(&self, py: Python<'_>) {
+        py.allow_threads(|| self.core.clean_up())
+    }
+}
+
+impl Drop for PythonLogger {
+    fn drop(&mut self) {
+        Python::with_gil(|py| py.allow_threads(|| self.core.clean_up()))
     }
 }
 
@@ -1,11 +1,88 @@
 #![crate_name = "dlog_rs"]
-use dlog_core::transforms::Transforms;
+use dlog_core::config::Config;
+use dlog_core::diagnostics::Diagnostics;
+use dlog_core::models::{Confirmation, Encoding, FlushPolicy, Priority, Status};
+use dlog_core::sinks::{
+    ElasticSinkOptions, FileSinkOptions, FluentSinkOptions, GelfSinkOptions, LokiSinkOptions, SplunkSinkOptions,
+};
+use dlog_core::stats::Stats;
+use dlog_core::transforms::{ConsoleOptions, IpAnonymization, RedactRule, Transform, Transforms};
 use log::Level;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use crate::logger::DlogLogger;
+use crate::logger::{convert_level, DlogLogger};
 
 mod logger;
 
+static CORE: RwLock<Option<Arc<dlog_core::Logger>>> = RwLock::new(None);
+
+/// Returns the counters collected by dlog, or `None` if dlog has not been configured yet.
+///
+/// # Examples
+///
+/// ```
+/// if let Some(stats) = dlog_rs::stats() {
+///     print!("{}", stats.to_prometheus());
+/// }
+/// ```
+pub fn stats() -> Option<Stats> {
+    core().map(|core| core.stats())
+}
+
+/// Returns the delivery status of dlog, or `None` if dlog has not been configured yet. This can be
+/// used to surface degraded logging in the health check of the application.
+pub fn status() -> Option<Status> {
+    core().map(|core| core.status())
+}
+
+/// Registers a callback which is invoked whenever the delivery status of dlog changes. Returns
+/// `false` if dlog has not been configured yet.
+///
+/// # Arguments
+///
+/// * `callback` - Invoked on the dlog background thread with the new status
+pub fn on_status_change(callback: impl Fn(Status) + Send + Sync + 'static) -> bool {
+    match core() {
+        Some(core) => {
+            core.on_status_change(callback);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Logs the message and blocks until it was accepted by all sinks or written to the backlog on disk.
+/// Use this for events which must not be lost, e.g. payments or permission changes.
+///
+/// # Arguments
+///
+/// * `level` - The level of the log
+/// * `message` - The message of the log
+/// * `timeout` - The maximum time to wait for the confirmation
+pub fn log_and_confirm(level: Level, message: impl Into<String>, timeout: Duration) -> Result<Confirmation, String> {
+    match core() {
+        Some(core) => core.log_and_confirm(convert_level(level), message.into(), timeout),
+        None => Err(String::from("[dlog] dlog has not been configured yet")),
+    }
+}
+
+/// Sends the remaining logs and stops dlog. As the logger of the `log` crate lives until the process
+/// exits, this should be called before returning from `main`. Repeated calls have no effect.
+pub fn shutdown() {
+    if let Some(core) = core() {
+        core.clean_up();
+    }
+}
+
+fn core() -> Option<Arc<dlog_core::Logger>> {
+    match CORE.read() {
+        Ok(val) => val.clone(),
+        Err(err) => err.into_inner().clone(),
+    }
+}
+
 /// Configures dlog with the given API_KEY and sensitive default values
 ///
 /// # Arguments
@@ -61,6 +138,8 @@ pub struct Builder {
     api_key: Option<String>,
     level: Option<Level>,
     transforms: Transforms,
+    config: Config,
+    error: Option<String>,
 }
 
 impl Builder {
@@ -70,6 +149,8 @@ impl Builder {
             api_key: None,
             level: None,
             transforms: Transforms::new(),
+            config: Config::new(),
+            error: None,
         }
     }
 
@@ -93,6 +174,17 @@ impl Builder {
         self
     }
 
+    /// Reads the API_KEY from a file, e.g. a mounted secret. The file is watched and the API_KEY is
+    /// rotated without restarting the service whenever the content of the file changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file containing the API_KEY
+    pub fn with_file_api_key(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.set_api_key_file(path);
+        self
+    }
+
     /// Sets the minimum level a log must have to be logged to dlog.
     ///
     /// # Arguments
@@ -119,9 +211,322 @@ impl Builder {
         self
     }
 
+    /// Adds a secret sanitizer which tries to mask bearer tokens, JWTs, AWS keys, GitHub tokens,
+    /// private keys and passwords in query parameters. This is a best effort sanitizer and there is no
+    /// guarantee that it will catch 100% of all secrets.
+    pub fn with_secret_sanitizer(mut self) -> Self {
+        self.transforms.add_secret_sanitizer(true);
+        self
+    }
+
+    /// Anonymizes all IPv4 and IPv6 addresses in the log messages, e.g. to comply with the GDPR.
+    ///
+    /// # Arguments
+    ///
+    /// * `anonymization` - Whether the addresses are truncated to their network prefix or replaced by a keyed hash
+    pub fn with_ip_anonymizer(mut self, anonymization: IpAnonymization) -> Self {
+        if let Err(err) = self.transforms.add_ip_anonymizer(anonymization) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Appends a custom transform to the end of the pipeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `transform` - The transform applied to every log
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Inserts a custom transform in front of the transform called `name`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of an already added transform, e.g. `email_sanitizer`
+    /// * `transform` - The transform applied to every log
+    pub fn with_transform_before(mut self, name: &str, transform: impl Transform + 'static) -> Self {
+        if let Err(err) = self.transforms.insert_before(name, transform) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Inserts a custom transform behind the transform called `name`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of an already added transform, e.g. `email_sanitizer`
+    /// * `transform` - The transform applied to every log
+    pub fn with_transform_after(mut self, name: &str, transform: impl Transform + 'static) -> Self {
+        if let Err(err) = self.transforms.insert_after(name, transform) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Removes all transforms called `name`, e.g. a sanitizer added by `configure`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the transforms to remove
+    pub fn without_transform(mut self, name: &str) -> Self {
+        self.transforms.remove(name);
+        self
+    }
+
+    /// Stops printing every log to the console.
+    pub fn without_console(mut self) -> Self {
+        self.transforms.set_console(false);
+        self
+    }
+
+    /// Configures how every log is printed to the console, e.g. as JSON lines on stderr.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The stream, format, colours, timestamp format and minimum priority of the console output
+    pub fn with_console_options(mut self, options: ConsoleOptions) -> Self {
+        if let Err(err) = self.transforms.set_console_options(options) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Redacts everything matching the given rules, e.g. customer IDs or internal tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - The named patterns and how their matches are replaced
+    pub fn with_regex_redaction(mut self, rules: Vec<RedactRule>) -> Self {
+        if let Err(err) = self.transforms.add_regex_redaction(rules) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Only sends logs whose message matches `include` and does not match `exclude`.
+    ///
+    /// # Arguments
+    ///
+    /// * `include` - The regular expression a message must match, if any
+    /// * `exclude` - The regular expression a message must not match, if any
+    pub fn with_regex_filter(mut self, include: Option<&str>, exclude: Option<&str>) -> Self {
+        if let Err(err) = self.transforms.add_regex_filter(include, exclude) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Suppresses noisy messages by sending at most `max` logs with identical text per `window`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - The number of identical messages sent per window
+    /// * `window` - The duration of a window
+    pub fn with_noise_filter(mut self, max: u32, window: Duration) -> Self {
+        self.transforms.add_noise_filter(max, window);
+        self
+    }
+
+    /// Corrects the timestamps of all logs if the local clock deviates from the dlog servers by
+    /// more than the given threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The clock skew tolerated before timestamps are corrected
+    pub fn with_clock_skew_correction(mut self, threshold: Duration) -> Self {
+        self.config.set_clock_skew_correction(threshold);
+        self
+    }
+
+    /// Limits the bytes per second uploaded to each remote sink for fresh logs and for backlog
+    /// replay. Both budgets are separate, so replaying a large backlog does not starve fresh logs.
+    /// Local sinks like files are not limited.
+    ///
+    /// # Arguments
+    ///
+    /// * `live` - The bytes per second for fresh logs, `None` for no limit
+    /// * `backlog` - The bytes per second for logs replayed from the backlog, `None` for no limit
+    pub fn with_bandwidth_limit(mut self, live: Option<u64>, backlog: Option<u64>) -> Self {
+        self.config.set_bandwidth_limit(live);
+        self.config.set_backlog_bandwidth_limit(backlog);
+        self
+    }
+
+    /// Sets the ingest endpoints in order of preference, e.g. a primary and a secondary regional
+    /// cluster. If an endpoint fails, logs are delivered to the next one before they are backlogged.
+    ///
+    /// # Arguments
+    ///
+    /// * `urls` - The urls of the ingest endpoints
+    pub fn with_endpoints(mut self, urls: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.set_endpoints(urls);
+        self
+    }
+
+    /// Sets the preferred wire format used to ingest logs into dlog. Lower-overhead formats such as
+    /// MessagePack are useful for high-volume services. Falls back to JSON if the server does not support it.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoding` - The preferred wire format
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.config.set_encoding(encoding);
+        self
+    }
+
+    /// Streams logs to dlog over a persistent connection as soon as they arrive instead of
+    /// sending them in batches every second. Useful for services requiring near-real-time delivery.
+    /// The connection uses the configured root and client certificates, but cannot go through a
+    /// proxy.
+    pub fn with_streaming(mut self) -> Self {
+        self.config.set_streaming(true);
+        self
+    }
+
+    /// Routes the ingestion through the given HTTP proxy, e.g. a corporate egress proxy. The
+    /// `HTTPS_PROXY` and `NO_PROXY` environmental variables are respected without this.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The url of the proxy, e.g. `http://proxy.internal:3128`
+    pub fn with_proxy(mut self, url: impl Into<String>) -> Self {
+        self.config.set_proxy(url);
+        self
+    }
+
+    /// Trusts an additional CA certificate, e.g. the internal CA of a self-hosted endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the PEM encoded CA certificate
+    pub fn with_root_certificate(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.add_root_certificate(path);
+        self
+    }
+
+    /// Presents a client certificate to the ingest endpoint for mutual TLS.
+    ///
+    /// # Arguments
+    ///
+    /// * `certificate` - The path to the PEM encoded client certificate
+    /// * `key` - The path to the PEM encoded private key of the certificate
+    pub fn with_client_certificate(mut self, certificate: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.config.set_client_certificate(certificate, key);
+        self
+    }
+
+    /// Additionally writes all logs to a local file which is rotated by size or time.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The path, format and rotation settings of the file
+    pub fn with_file_sink(self, options: FileSinkOptions) -> Self {
+        self.configure(|config| config.add_file_sink(options))
+    }
+
+    /// Additionally pushes all logs into a Grafana Loki instance, e.g. during development.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The Loki url, static labels and encoding
+    pub fn with_loki_sink(self, options: LokiSinkOptions) -> Self {
+        self.configure(|config| config.add_loki_sink(options))
+    }
+
+    /// Additionally indexes all logs into Elasticsearch or OpenSearch using the `_bulk` API.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The cluster url, index pattern and credentials
+    pub fn with_elastic_sink(self, options: ElasticSinkOptions) -> Self {
+        self.configure(|config| config.add_elastic_sink(options))
+    }
+
+    /// Additionally forwards all logs to a Fluentd or Fluent Bit agent using the Fluent Forward protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The agent address, tag, forward mode and whether acks are requested
+    pub fn with_fluent_sink(self, options: FluentSinkOptions) -> Self {
+        self.configure(|config| config.add_fluent_sink(options))
+    }
+
+    /// Additionally sends all logs to a Splunk HTTP Event Collector.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The collector url, token and optional index, source and sourcetype
+    pub fn with_splunk_sink(self, options: SplunkSinkOptions) -> Self {
+        self.configure(|config| config.add_splunk_sink(options))
+    }
+
+    /// Additionally sends all logs to Graylog using GELF over UDP or TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The Graylog input address, transport, compression and additional fields
+    pub fn with_gelf_sink(self, options: GelfSinkOptions) -> Self {
+        self.configure(|config| config.add_gelf_sink(options))
+    }
+
+    /// Sets where dlog reports its own problems, e.g. connection errors or backlog retries. By
+    /// default they are written to stderr.
+    ///
+    /// # Arguments
+    ///
+    /// * `diagnostics` - Either stderr, a callback, silent or the log stream with a rate limit
+    pub fn with_diagnostics(mut self, diagnostics: Diagnostics) -> Self {
+        self.config.set_diagnostics(diagnostics);
+        self
+    }
+
+    /// Sets when logs of the given priority are sent. By default `Critical` logs are sent durably
+    /// and all other logs are sent in batches.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority` - The priority the policy applies to
+    /// * `policy` - Whether the logs are batched, sent immediately or sent immediately and durably
+    pub fn with_flush_policy(mut self, priority: Priority, policy: FlushPolicy) -> Self {
+        self.config.set_flush_policy(priority, policy);
+        self
+    }
+
+    /// Sets how long dlog waits for the remaining logs to be sent during shutdown.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - The maximum time spent sending the remaining logs
+    pub fn with_drain_deadline(mut self, deadline: Duration) -> Self {
+        self.config.set_drain_deadline(deadline);
+        self
+    }
+
+    /// Installs a handler for SIGTERM and SIGINT which sends the remaining logs before the process
+    /// exits.
+    pub fn with_signal_handler(mut self) -> Self {
+        self.config.set_signal_handler(true);
+        self
+    }
+
+    /// Disables the ingestion into the dlog cloud. This is useful during development or in
+    /// air-gapped deployments where logs should only be written to the configured file sinks.
+    pub fn without_cloud(mut self) -> Self {
+        self.config.set_cloud(false);
+        self
+    }
+
     /// Consumes the builder and configures dlog according to the builders configuration.
     pub fn build(self) {
-        let native = match dlog_core::Logger::new(self.api_key.unwrap_or_default(), self.transforms) {
+        if let Some(err) = self.error {
+            panic!("[dlog] Failed to configure dlog: {}", err)
+        }
+
+        let native = match dlog_core::Logger::new(self.api_key.unwrap_or_default(), self.transforms, self.config) {
             Err(err) => panic!("[dlog] Failed to configure dlog: {}", err),
             Ok(val) => val,
         };
@@ -129,6 +534,12 @@ impl Builder {
         let level = self.level.unwrap_or(Level::Debug);
         log::set_max_level(level.to_level_filter());
 
+        let native = Arc::new(native);
+        match CORE.write() {
+            Ok(mut val) => *val = Some(native.clone()),
+            Err(err) => *err.into_inner() = Some(native.clone()),
+        }
+
         let logger = DlogLogger::new(native, level);
         if let Err(err) = log::set_boxed_logger(Box::new(logger)) {
             panic!("{}", err)
@@ -136,6 +547,15 @@ impl Builder {
     }
 }
 
+impl Builder {
+    fn configure(mut self, apply: impl FnOnce(&mut Config) -> Result<(), String>) -> Self {
+        if let Err(err) = apply(&mut self.config) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+}
+
 impl Default for Builder {
     fn default() -> Self {
         Self::new()
@@ -1,14 +1,15 @@
 use log::{Level, Metadata, Record};
+use std::sync::Arc;
 
 use dlog_core::models::Priority;
 
 pub struct DlogLogger {
-    core: dlog_core::Logger,
+    core: Arc<dlog_core::Logger>,
     level: Level,
 }
 
 impl DlogLogger {
-    pub fn new(core: dlog_core::Logger, level: Level) -> Self {
+    pub fn new(core: Arc<dlog_core::Logger>, level: Level) -> Self {
         Self { core, level }
     }
 }
@@ -31,7 +32,7 @@ impl log::Log for DlogLogger {
     }
 }
 
-fn convert_level(level: Level) -> Priority {
+pub(crate) fn convert_level(level: Level) -> Priority {
     match level {
         Level::Error => Priority::Error,
         Level::Warn => Priority::Warning,